@@ -0,0 +1,277 @@
+// Structure-of-arrays copy of a population of neural networks. The parameters of all
+// networks are stored contiguously per layer and evaluated in a single pass, reusing
+// the same buffers every step so inference does not allocate.
+use crate::neural_network::*;
+
+// A network together with the hidden state it continues from
+type Member<'a> = (&'a NeuralNetwork, &'a [f32]);
+
+// Networks that all share the same layer sizes and recurrent layers
+#[derive(Clone, Debug)]
+pub struct BatchedNetworks {
+    count: usize,
+    layer_sizes: Vec<usize>,
+    recurrent: Vec<bool>,
+    weights: Vec<f32>,            // Per layer: [network][output][input]
+    recurrent_weights: Vec<f32>,  // Per recurrent layer: [network][output][output]
+    biases: Vec<f32>,             // Per layer: [network][output]
+    activations: Vec<Activation>, // [network][layer]
+    states: Vec<f32>,             // [network][hidden state], see NeuralNetwork::forward
+    inputs: Vec<f32>,             // [network][input]
+    outputs: Vec<f32>,            // [network][output] after forward
+}
+
+impl BatchedNetworks {
+    // networks pairs every network with its hidden state. Returns None if the networks do
+    // not all have the same topology or a hidden state has the wrong length.
+    pub fn new(networks: &[Member]) -> Option<Self> {
+        let topology = networks.first()?.0.topology();
+        let state_size = networks.first()?.0.hidden_state_size();
+        if networks.iter().any(|(network, state)| {
+            !network.topology().is_compatible(&topology) || state.len() != state_size
+        }) {
+            return None;
+        }
+        let count = networks.len();
+        let layer_count = topology.layer_sizes.len() - 1;
+        let mut weights = Vec::new();
+        let mut recurrent_weights = Vec::new();
+        let mut biases = Vec::new();
+        for layer in 0..layer_count {
+            for (network, _) in networks {
+                let parameters = network.layer_parameters().nth(layer)?;
+                weights.extend(parameters.weights.iter().flatten());
+                recurrent_weights.extend(parameters.recurrent_weights.iter().flatten());
+                biases.extend_from_slice(parameters.biases);
+            }
+        }
+        let activations = networks
+            .iter()
+            .flat_map(|(network, _)| network.activations())
+            .collect();
+        let states = networks
+            .iter()
+            .flat_map(|(_, state)| state.iter().copied())
+            .collect();
+        let widest = topology.layer_sizes.iter().copied().max().unwrap_or(0);
+        Some(Self {
+            count,
+            layer_sizes: topology.layer_sizes,
+            recurrent: topology.recurrent,
+            weights,
+            recurrent_weights,
+            biases,
+            activations,
+            states,
+            inputs: vec![0.0; count * widest],
+            outputs: vec![0.0; count * widest],
+        })
+    }
+
+    pub fn input_size(&self) -> usize {
+        self.layer_sizes[0]
+    }
+
+    pub fn output_size(&self) -> usize {
+        self.layer_sizes[self.layer_sizes.len() - 1]
+    }
+
+    // Input slot of one network, filled before calling forward
+    pub fn input_mut(&mut self, network: usize) -> &mut [f32] {
+        let size = self.input_size();
+        &mut self.inputs[network * size..(network + 1) * size]
+    }
+
+    // Output of one network from the last call to forward
+    pub fn output(&self, network: usize) -> &[f32] {
+        let size = self.output_size();
+        &self.outputs[network * size..(network + 1) * size]
+    }
+
+    fn state_size(&self) -> usize {
+        self.layer_sizes[1..]
+            .iter()
+            .zip(&self.recurrent)
+            .filter(|(_, recurrent)| **recurrent)
+            .map(|(size, _)| size)
+            .sum()
+    }
+
+    // Hidden state of one network after the last call to forward
+    pub fn hidden_state(&self, network: usize) -> &[f32] {
+        let size = self.state_size();
+        &self.states[network * size..(network + 1) * size]
+    }
+
+    // Evaluate every network on its input slot, advancing the hidden states
+    pub fn forward(&mut self) {
+        let layer_count = self.layer_sizes.len() - 1;
+        let state_size = self.state_size();
+        let mut weight_offset = 0;
+        let mut recurrent_offset = 0;
+        let mut bias_offset = 0;
+        let mut state_offset = 0; // Start of this layer within a hidden state
+        for layer in 0..layer_count {
+            let (in_size, out_size) = (self.layer_sizes[layer], self.layer_sizes[layer + 1]);
+            let recurrent = self.recurrent[layer];
+            for network in 0..self.count {
+                let input = &self.inputs[network * in_size..(network + 1) * in_size];
+                let activation = self.activations[network * layer_count + layer];
+                let state_start = network * state_size + state_offset;
+                for neuron in 0..out_size {
+                    let row = network * out_size + neuron;
+                    let weights = &self.weights
+                        [weight_offset + row * in_size..weight_offset + (row + 1) * in_size];
+                    let mut sum = weights.iter().zip(input).map(|(w, i)| w * i).sum::<f32>()
+                        + self.biases[bias_offset + row];
+                    if recurrent {
+                        let recurrent_weights = &self.recurrent_weights[recurrent_offset
+                            + row * out_size
+                            ..recurrent_offset + (row + 1) * out_size];
+                        let state = &self.states[state_start..state_start + out_size];
+                        sum += recurrent_weights
+                            .iter()
+                            .zip(state)
+                            .map(|(w, s)| w * s)
+                            .sum::<f32>();
+                    }
+                    self.outputs[row] = activation.apply(sum);
+                }
+                if recurrent {
+                    // Every neuron has read the previous state, so it can be replaced
+                    self.states[state_start..state_start + out_size].copy_from_slice(
+                        &self.outputs[network * out_size..(network + 1) * out_size],
+                    );
+                }
+            }
+            weight_offset += self.count * out_size * in_size;
+            bias_offset += self.count * out_size;
+            if recurrent {
+                recurrent_offset += self.count * out_size * out_size;
+                state_offset += out_size;
+            }
+            // The outputs of this layer are the inputs of the next
+            std::mem::swap(&mut self.inputs, &mut self.outputs);
+        }
+        std::mem::swap(&mut self.inputs, &mut self.outputs);
+    }
+}
+
+// A population split into one batch per network shape
+#[derive(Clone, Debug, Default)]
+pub struct BatchedPopulation {
+    groups: Vec<(BatchedNetworks, Vec<usize>)>, // Batch and the bird index of each network
+}
+
+impl BatchedPopulation {
+    // networks yields each bird's index together with its network and hidden state
+    pub fn new<'a>(
+        networks: impl IntoIterator<Item = (usize, &'a NeuralNetwork, &'a [f32])>,
+    ) -> Self {
+        let mut shapes: Vec<(Topology, Vec<usize>, Vec<Member>)> = Vec::new();
+        for (index, network, state) in networks {
+            let topology = network.topology();
+            match shapes
+                .iter_mut()
+                .find(|(shape, _, _)| shape.is_compatible(&topology))
+            {
+                Some((_, indices, members)) => {
+                    indices.push(index);
+                    members.push((network, state));
+                }
+                None => shapes.push((topology, vec![index], vec![(network, state)])),
+            }
+        }
+        let groups = shapes
+            .into_iter()
+            .filter_map(|(_, indices, members)| Some((BatchedNetworks::new(&members)?, indices)))
+            .collect();
+        Self { groups }
+    }
+
+    // Evaluate every network on its bird's inputs. decide receives the bird index, the
+    // network output and the new hidden state.
+    pub fn forward<const N: usize>(
+        &mut self,
+        inputs: impl Fn(usize) -> [f32; N],
+        mut decide: impl FnMut(usize, &[f32], &[f32]),
+    ) {
+        for (batch, indices) in self.groups.iter_mut() {
+            for (network, index) in indices.iter().enumerate() {
+                batch.input_mut(network).copy_from_slice(&inputs(*index));
+            }
+            batch.forward();
+            for (network, index) in indices.iter().enumerate() {
+                decide(*index, batch.output(network), batch.hidden_state(network));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initialization::WeightInit;
+    use rand::prelude::*;
+
+    #[test]
+    fn batch_matches_each_network_on_its_own() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let recurrent = Topology::new(
+            &[4, 5, 3, 1],
+            &[Activation::Tanh, Activation::LeakyRelu, Activation::Sigmoid],
+            &[true, false, true],
+        );
+        let feedforward = Topology::new(&[4, 6, 1], &[Activation::Relu, Activation::Sigmoid], &[]);
+        // Two shapes interleaved, so the population is split into two batches
+        let networks: Vec<NeuralNetwork> = (0..6)
+            .map(|index| {
+                let topology = if index % 2 == 0 {
+                    &recurrent
+                } else {
+                    &feedforward
+                };
+                NeuralNetwork::from_topology(topology, WeightInit::Xavier, &mut rng)
+            })
+            .collect();
+        let mut states: Vec<Vec<f32>> = networks
+            .iter()
+            .map(|network| {
+                (0..network.hidden_state_size())
+                    .map(|_| rng.gen_range(-1.0..1.0))
+                    .collect()
+            })
+            .collect();
+        let mut batch_states = states.clone();
+        let mut batch = BatchedPopulation::new(
+            networks
+                .iter()
+                .zip(&states)
+                .enumerate()
+                .map(|(index, (network, state))| (index, network, state.as_slice())),
+        );
+
+        for step in 0..5 {
+            let inputs = |index: usize| {
+                let x = (step * 7 + index) as f32;
+                [x.sin(), x.cos(), 0.1 * x, -0.5]
+            };
+            let mut batch_outputs = vec![Vec::new(); networks.len()];
+            batch.forward(inputs, |index, output, state| {
+                batch_outputs[index] = output.to_vec();
+                batch_states[index] = state.to_vec();
+            });
+            for (index, network) in networks.iter().enumerate() {
+                let output = network.forward(&inputs(index), &mut states[index]);
+                assert_eq!(output.len(), batch_outputs[index].len());
+                for (a, b) in output.iter().zip(&batch_outputs[index]) {
+                    assert!((a - b).abs() < 1e-5, "step {} network {}", step, index);
+                }
+                assert_eq!(states[index].len(), batch_states[index].len());
+                for (a, b) in states[index].iter().zip(&batch_states[index]) {
+                    assert!((a - b).abs() < 1e-5, "step {} network {}", step, index);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::optimizer::TrainingAlgorithm;
+
+pub const USAGE: &str =
+    "Usage: dont_crash [--headless] [--generations N] [--target-score S] [--genome-dir DIR] [--seed SEED]
+                  [--population N] [--neat] [--algorithm NAME] [--pretrain FILE]
+
+  --headless          Train without a window, as fast as the CPU allows
+  --generations N     Stop headless training after N generations
+  --target-score S    Stop headless training once a bird scores S
+  --genome-dir DIR    Directory the hall of fame is saved to when headless training stops
+  --seed SEED         Seed for all randomness of the run, random if omitted
+  --population N      Number of birds in each generation
+  --neat              Evolve NEAT genomes instead of fixed-architecture networks
+  --algorithm NAME    Training algorithm of fixed-architecture networks: ga (default), cma-es,
+                      openai-es, de, pso
+  --pretrain FILE     Start from a network trained on the demonstrations in FILE";
+
+#[derive(Clone, Debug, Default)]
+pub struct CliArgs {
+    pub headless: bool,
+    pub generations: Option<usize>,
+    pub target_score: Option<f32>,
+    pub genome_directory: Option<String>,
+    pub seed: Option<u64>,
+    pub population_size: Option<usize>,
+    pub neat: bool,
+    pub algorithm: Option<TrainingAlgorithm>,
+    pub pretrain: Option<String>, // Dataset of demonstrations
+}
+
+impl CliArgs {
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut cli_args = CliArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--headless" => cli_args.headless = true,
+                "--generations" => cli_args.generations = Some(parse_value(&arg, args.next())?),
+                "--target-score" => cli_args.target_score = Some(parse_value(&arg, args.next())?),
+                "--genome-dir" => cli_args.genome_directory = Some(parse_value(&arg, args.next())?),
+                "--population" => cli_args.population_size = Some(parse_value(&arg, args.next())?),
+                "--seed" => cli_args.seed = Some(parse_value(&arg, args.next())?),
+                "--neat" => cli_args.neat = true,
+                "--algorithm" => cli_args.algorithm = Some(parse_value(&arg, args.next())?),
+                "--pretrain" => cli_args.pretrain = Some(parse_value(&arg, args.next())?),
+                _ => return Err(format!("unknown argument '{}'", arg)),
+            }
+        }
+        Ok(cli_args)
+    }
+}
+
+fn parse_value<T: std::str::FromStr>(flag: &str, value: Option<String>) -> Result<T, String> {
+    let value = value.ok_or_else(|| format!("{} expects a value", flag))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value '{}' for {}", value, flag))
+}
@@ -0,0 +1,313 @@
+// Covariance Matrix Adaptation Evolution Strategy. Each generation is sampled from a
+// multivariate normal over the genome whose mean, step size and covariance follow the
+// fittest samples. Settings follow Hansen's tutorial "The CMA Evolution Strategy".
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+
+use crate::optimizer::ParameterOptimizer;
+
+pub struct CmaEs {
+    dimension: usize,
+    population_size: usize,
+    weights: Vec<f64>, // Recombination weights of the fittest half, summing to 1
+    mu_eff: f64,
+    c_sigma: f64, // Learning rate of the step size path
+    d_sigma: f64, // Damping of the step size
+    c_c: f64,     // Learning rate of the covariance path
+    c_1: f64,     // Learning rate of the rank-one update
+    c_mu: f64,    // Learning rate of the rank-mu update
+    chi_n: f64,   // Expected length of a standard normal vector
+    mean: Vec<f64>,
+    sigma: f64,
+    covariance: Vec<Vec<f64>>,
+    eigenvectors: Vec<Vec<f64>>, // Columns of B in C = B D² Bᵀ
+    scales: Vec<f64>,            // Diagonal of D
+    path_sigma: Vec<f64>,
+    path_c: Vec<f64>,
+    samples: Vec<Vec<f64>>, // Genomes of the last ask
+    generation: usize,
+}
+
+impl CmaEs {
+    pub fn new(start: &[f32], sigma: f32, population_size: usize) -> Self {
+        let n = start.len() as f64;
+        let population_size = population_size.max(2);
+        let mu = population_size / 2;
+        let raw: Vec<f64> = (0..mu)
+            .map(|rank| (mu as f64 + 0.5).ln() - (rank as f64 + 1.0).ln())
+            .collect();
+        let total: f64 = raw.iter().sum();
+        let weights: Vec<f64> = raw.iter().map(|w| w / total).collect();
+        let mu_eff = 1.0 / weights.iter().map(|w| w * w).sum::<f64>();
+        let c_sigma = (mu_eff + 2.0) / (n + mu_eff + 5.0);
+        let c_1 = 2.0 / ((n + 1.3).powi(2) + mu_eff);
+        let dimension = start.len();
+        Self {
+            dimension,
+            population_size,
+            mu_eff,
+            c_sigma,
+            d_sigma: 1.0 + 2.0 * (((mu_eff - 1.0) / (n + 1.0)).sqrt() - 1.0).max(0.0) + c_sigma,
+            c_c: (4.0 + mu_eff / n) / (n + 4.0 + 2.0 * mu_eff / n),
+            c_1,
+            c_mu: (1.0 - c_1)
+                .min(2.0 * (mu_eff - 2.0 + 1.0 / mu_eff) / ((n + 2.0).powi(2) + mu_eff)),
+            chi_n: n.sqrt() * (1.0 - 1.0 / (4.0 * n) + 1.0 / (21.0 * n * n)),
+            weights,
+            mean: start.iter().map(|x| *x as f64).collect(),
+            sigma: sigma.max(f32::EPSILON) as f64,
+            covariance: identity(dimension),
+            eigenvectors: identity(dimension),
+            scales: vec![1.0; dimension],
+            path_sigma: vec![0.0; dimension],
+            path_c: vec![0.0; dimension],
+            samples: Vec::new(),
+            generation: 0,
+        }
+    }
+
+    // B D z for a standard normal z, a sample of N(0, C)
+    fn correlate(&self, z: &[f64]) -> Vec<f64> {
+        let scaled: Vec<f64> = z.iter().zip(&self.scales).map(|(z, d)| z * d).collect();
+        (0..self.dimension)
+            .map(|row| dot(&self.eigenvectors[row], &scaled))
+            .collect()
+    }
+
+    // C^-1/2 y = B D⁻¹ Bᵀ y
+    fn whiten(&self, y: &[f64]) -> Vec<f64> {
+        let projected: Vec<f64> = (0..self.dimension)
+            .map(|column| {
+                let along: f64 = (0..self.dimension)
+                    .map(|row| self.eigenvectors[row][column] * y[row])
+                    .sum();
+                along / self.scales[column]
+            })
+            .collect();
+        (0..self.dimension)
+            .map(|row| dot(&self.eigenvectors[row], &projected))
+            .collect()
+    }
+}
+
+impl ParameterOptimizer for CmaEs {
+    fn ask(&mut self, rng: &mut StdRng) -> Vec<Vec<f32>> {
+        self.samples = (0..self.population_size)
+            .map(|_| {
+                let z: Vec<f64> = (0..self.dimension)
+                    .map(|_| rng.sample(StandardNormal))
+                    .collect();
+                let y = self.correlate(&z);
+                self.mean
+                    .iter()
+                    .zip(&y)
+                    .map(|(m, y)| m + self.sigma * y)
+                    .collect()
+            })
+            .collect();
+        self.samples
+            .iter()
+            .map(|sample| sample.iter().map(|x| *x as f32).collect())
+            .collect()
+    }
+
+    fn tell(&mut self, fitness: &[f32]) {
+        if fitness.len() != self.samples.len() || self.samples.is_empty() {
+            return;
+        }
+        let n = self.dimension;
+        let mut order: Vec<usize> = (0..fitness.len()).collect();
+        order.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+        // Steps of the fittest samples from the old mean, in units of sigma
+        let steps: Vec<Vec<f64>> = order
+            .iter()
+            .take(self.weights.len())
+            .map(|index| {
+                self.samples[*index]
+                    .iter()
+                    .zip(&self.mean)
+                    .map(|(x, m)| (x - m) / self.sigma)
+                    .collect()
+            })
+            .collect();
+        let mean_step: Vec<f64> = (0..n)
+            .map(|i| {
+                self.weights
+                    .iter()
+                    .zip(&steps)
+                    .map(|(w, step)| w * step[i])
+                    .sum()
+            })
+            .collect();
+        for (m, step) in self.mean.iter_mut().zip(&mean_step) {
+            *m += self.sigma * step;
+        }
+
+        // Evolution paths
+        let whitened = self.whiten(&mean_step);
+        let sigma_rate = (self.c_sigma * (2.0 - self.c_sigma) * self.mu_eff).sqrt();
+        for (p, w) in self.path_sigma.iter_mut().zip(&whitened) {
+            *p = (1.0 - self.c_sigma) * *p + sigma_rate * w;
+        }
+        self.generation += 1;
+        let path_length = dot(&self.path_sigma, &self.path_sigma).sqrt();
+        let decay = 1.0 - (1.0 - self.c_sigma).powi(2 * self.generation as i32);
+        // Hold the covariance path while the step size path is unusually long
+        let h_sigma = if path_length / decay.sqrt() / self.chi_n < 1.4 + 2.0 / (n as f64 + 1.0) {
+            1.0
+        } else {
+            0.0
+        };
+        let c_rate = (self.c_c * (2.0 - self.c_c) * self.mu_eff).sqrt();
+        for (p, step) in self.path_c.iter_mut().zip(&mean_step) {
+            *p = (1.0 - self.c_c) * *p + h_sigma * c_rate * step;
+        }
+
+        // Rank-one and rank-mu covariance update
+        let correction = (1.0 - h_sigma) * self.c_c * (2.0 - self.c_c);
+        let keep = 1.0 - self.c_1 - self.c_mu + self.c_1 * correction;
+        for i in 0..n {
+            for j in 0..=i {
+                let rank_mu: f64 = self
+                    .weights
+                    .iter()
+                    .zip(&steps)
+                    .map(|(w, step)| w * step[i] * step[j])
+                    .sum();
+                let value = keep * self.covariance[i][j]
+                    + self.c_1 * self.path_c[i] * self.path_c[j]
+                    + self.c_mu * rank_mu;
+                self.covariance[i][j] = value;
+                self.covariance[j][i] = value;
+            }
+        }
+
+        self.sigma *= ((self.c_sigma / self.d_sigma) * (path_length / self.chi_n - 1.0)).exp();
+
+        let (eigenvalues, eigenvectors) = symmetric_eigen(&self.covariance);
+        self.scales = eigenvalues.iter().map(|v| v.max(1e-20).sqrt()).collect();
+        self.eigenvectors = eigenvectors;
+    }
+
+    fn status(&self) -> String {
+        let (smallest, largest) = self
+            .scales
+            .iter()
+            .fold((f64::INFINITY, 0.0_f64), |(low, high), d| {
+                (low.min(*d), high.max(*d))
+            });
+        format!(
+            "Step size {:.4} | axis ratio {:.1}",
+            self.sigma,
+            largest / smallest
+        )
+    }
+}
+
+fn identity(size: usize) -> Vec<Vec<f64>> {
+    (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|column| (row == column) as u8 as f64)
+                .collect()
+        })
+        .collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+// Eigenvalues and eigenvectors (as columns) of a symmetric matrix, by cyclic Jacobi
+// rotations. Genomes are small enough that this costs little next to a generation.
+fn symmetric_eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    let mut vectors = identity(n);
+    for _ in 0..50 {
+        let off_diagonal: f64 = (0..n)
+            .flat_map(|i| (0..i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off_diagonal < 1e-22 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                // Jᵀ A J: rotate columns p and q, then rows p and q, then the eigenvectors
+                for row in a.iter_mut() {
+                    let (akp, akq) = (row[p], row[q]);
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (head, tail) = a.split_at_mut(q);
+                for (apk, aqk) in head[p].iter_mut().zip(tail[0].iter_mut()) {
+                    let (x, y) = (*apk, *aqk);
+                    *apk = c * x - s * y;
+                    *aqk = s * x + c * y;
+                }
+                for row in vectors.iter_mut() {
+                    let (vp, vq) = (row[p], row[q]);
+                    row[p] = c * vp - s * vq;
+                    row[q] = s * vp + c * vq;
+                }
+            }
+        }
+    }
+    ((0..n).map(|i| a[i][i]).collect(), vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A v = lambda v for every eigenpair, and the eigenvectors are orthonormal
+    fn assert_eigen_decomposition(matrix: &[Vec<f64>]) {
+        let n = matrix.len();
+        let (values, vectors) = symmetric_eigen(matrix);
+        for column in 0..n {
+            for row in 0..n {
+                let product: f64 = (0..n).map(|k| matrix[row][k] * vectors[k][column]).sum();
+                assert!((product - values[column] * vectors[row][column]).abs() < 1e-9);
+            }
+            for other in 0..n {
+                let dot: f64 = (0..n).map(|k| vectors[k][column] * vectors[k][other]).sum();
+                let expected = if column == other { 1.0 } else { 0.0 };
+                assert!((dot - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn eigenvalues_of_a_known_matrix() {
+        let (mut values, _) = symmetric_eigen(&[vec![2.0, 1.0], vec![1.0, 2.0]]);
+        values.sort_by(f64::total_cmp);
+        assert!((values[0] - 1.0).abs() < 1e-12 && (values[1] - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn eigen_decomposition_of_random_symmetric_matrices() {
+        let mut rng = StdRng::seed_from_u64(4);
+        for n in [1, 3, 8] {
+            let random: Vec<Vec<f64>> = (0..n)
+                .map(|_| (0..n).map(|_| rng.gen_range(-1.0..1.0)).collect())
+                .collect();
+            // B + B^T is symmetric
+            let matrix: Vec<Vec<f64>> = (0..n)
+                .map(|row| {
+                    (0..n)
+                        .map(|column| random[row][column] + random[column][row])
+                        .collect()
+                })
+                .collect();
+            assert_eigen_decomposition(&matrix);
+        }
+    }
+}
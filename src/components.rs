@@ -1,18 +1,73 @@
+use crate::batch::BatchedPopulation;
+use crate::dataset::Demonstration;
+use crate::neat::{NeatGenome, NeatNetwork};
 use crate::neural_network::*;
+use crate::selection::Individual;
+use crate::simulation::FlappyWorld;
 use bevy::prelude::*;
 
+// Sprite of one half of a simulated pipe pair
 #[derive(Clone, Debug, Component)]
 pub struct Pipe {
-    pub velocity: f32,
-    pub gap: f32,        // Width of gap
-    pub gap_center: f32, // Vertical center of gap
-    pub bird_passed: bool,
+    pub id: u64, // PipePair::id in the simulation
 }
 #[derive(Clone, Debug, Component)]
 pub struct Bird {
-    pub velocity: f32,
-    pub score: f32,   // Number of pipes passed
-    pub fitness: f32, // Second alive
-    pub dead: bool,
-    pub neural_network: NeuralNetwork,
+    pub index: usize, // Index of the bird in the simulation
+    pub brain: Brain,
+    pub hidden_state: Vec<f32>, // Previous outputs of the recurrent layers, zero at spawn
 }
+
+// What decides when a bird jumps
+#[derive(Clone, Debug)]
+pub enum Brain {
+    Network(NeuralNetwork), // Fixed architecture, see network_size
+    Neat(NeatGenome),       // Topology evolves with the weights
+    Human,                  // Jumps when the player presses Space
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BrainKind {
+    #[default]
+    Network,
+    Neat,
+    Human,
+}
+
+impl BrainKind {
+    pub const ALL: [BrainKind; 3] = [BrainKind::Network, BrainKind::Neat, BrainKind::Human];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BrainKind::Network => "Fixed Network",
+            BrainKind::Neat => "NEAT",
+            BrainKind::Human => "Human Player",
+        }
+    }
+}
+
+// The simulation every bird lives in
+#[derive(Clone, Debug, Resource, Deref, DerefMut)]
+pub struct Simulation(pub FlappyWorld);
+
+// Jump decision of every bird for the next step, indexed like the simulation
+#[derive(Clone, Debug, Default, Resource)]
+pub struct BirdActions(pub Vec<bool>);
+
+// Evaluation-ready copy of the brains of all birds, rebuilt whenever a Bird changes
+#[derive(Clone, Debug, Default, Resource)]
+pub struct PopulationBrains {
+    pub networks: BatchedPopulation,
+    pub neat: Vec<(usize, NeatNetwork)>, // Bird index and its compiled genome
+}
+
+// Input of the player and what they did in the current flight
+#[derive(Clone, Debug, Default, Resource)]
+pub struct HumanPlay {
+    pub jump_requested: bool,          // Space was pressed since the last step
+    pub recording: Vec<Demonstration>, // Every step of the flight so far
+}
+
+// Every bird of the current generation that has crashed, in the order they crashed
+#[derive(Clone, Debug, Default, Resource)]
+pub struct EvaluatedPopulation(pub Vec<Individual>);
@@ -0,0 +1,428 @@
+// Recombination of two parent networks into a child
+use rand::prelude::*;
+
+use crate::neural_network::{NetworkError, NeuralNetwork};
+
+// Builds a child from two parents. Fails if the genes of the parents do not line up.
+pub trait CrossoverOperator {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError>;
+}
+
+// Set each gene of a copy of parent1 from the matching genes of both parents
+fn combine_genes(
+    parent1: &NeuralNetwork,
+    parent2: &NeuralNetwork,
+    mut gene: impl FnMut(usize, f32, f32) -> f32,
+) -> Result<NeuralNetwork, NetworkError> {
+    parent2.check_compatible(parent1)?;
+    let mut child = parent1.clone();
+    for (index, (child_gene, (gene1, gene2))) in child
+        .genes_mut()
+        .zip(parent1.genes().zip(parent2.genes()))
+        .enumerate()
+    {
+        *child_gene = gene(index, *gene1, *gene2);
+    }
+    Ok(child)
+}
+
+// Midpoint of both parents
+pub struct AverageCrossover;
+
+impl CrossoverOperator for AverageCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        _rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        combine_genes(parent1, parent2, |_, gene1, gene2| (gene1 + gene2) / 2.0)
+    }
+}
+
+// Every gene from either parent with equal chance
+pub struct UniformCrossover;
+
+impl CrossoverOperator for UniformCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        combine_genes(parent1, parent2, |_, gene1, gene2| {
+            if rng.gen_bool(0.5) {
+                gene1
+            } else {
+                gene2
+            }
+        })
+    }
+}
+
+// Genes before a random cut from parent1, the rest from parent2
+pub struct SinglePointCrossover;
+
+impl CrossoverOperator for SinglePointCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        let cut = rng.gen_range(0..=parent1.genes().count());
+        combine_genes(
+            parent1,
+            parent2,
+            |index, gene1, gene2| {
+                if index < cut {
+                    gene1
+                } else {
+                    gene2
+                }
+            },
+        )
+    }
+}
+
+// Genes between two random cuts from parent2, the rest from parent1
+pub struct TwoPointCrossover;
+
+impl CrossoverOperator for TwoPointCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        let gene_count = parent1.genes().count();
+        let (a, b) = (rng.gen_range(0..=gene_count), rng.gen_range(0..=gene_count));
+        let (start, end) = (a.min(b), a.max(b));
+        combine_genes(parent1, parent2, |index, gene1, gene2| {
+            if (start..end).contains(&index) {
+                gene2
+            } else {
+                gene1
+            }
+        })
+    }
+}
+
+// Every layer from either parent with equal chance
+pub struct LayerSwapCrossover;
+
+impl CrossoverOperator for LayerSwapCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        parent2.check_compatible(parent1)?;
+        let mut child = parent1.clone();
+        for layer in 0..parent1.layer_sizes().len().saturating_sub(1) {
+            if rng.gen_bool(0.5) {
+                child.copy_layer(parent2, layer);
+            }
+        }
+        Ok(child)
+    }
+}
+
+// Every neuron, with its incoming weights and bias, from either parent with equal chance
+pub struct NeuronSwapCrossover;
+
+impl CrossoverOperator for NeuronSwapCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        parent2.check_compatible(parent1)?;
+        let mut child = parent1.clone();
+        for (layer, neurons) in parent1.layer_sizes().into_iter().skip(1).enumerate() {
+            for neuron in 0..neurons {
+                if rng.gen_bool(0.5) {
+                    child.copy_neuron(parent2, layer, neuron);
+                }
+            }
+        }
+        Ok(child)
+    }
+}
+
+// BLX-alpha: each gene drawn uniformly from the range spanned by both parents, widened
+// by alpha times its length on either side
+pub struct BlendCrossover {
+    pub alpha: f32,
+}
+
+impl CrossoverOperator for BlendCrossover {
+    fn crossover(
+        &self,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+        rng: &mut dyn RngCore,
+    ) -> Result<NeuralNetwork, NetworkError> {
+        combine_genes(parent1, parent2, |_, gene1, gene2| {
+            let extension = self.alpha.max(0.0) * (gene1 - gene2).abs();
+            let (low, high) = (gene1.min(gene2) - extension, gene1.max(gene2) + extension);
+            if low < high {
+                rng.gen_range(low..high)
+            } else {
+                gene1
+            }
+        })
+    }
+}
+
+// Operator choice for the GUI. The alpha of blend crossover lives in GuiParameters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CrossoverKind {
+    #[default]
+    Average,
+    Uniform,
+    SinglePoint,
+    TwoPoint,
+    LayerSwap,
+    NeuronSwap,
+    Blend,
+}
+
+impl CrossoverKind {
+    pub const ALL: [CrossoverKind; 7] = [
+        CrossoverKind::Average,
+        CrossoverKind::Uniform,
+        CrossoverKind::SinglePoint,
+        CrossoverKind::TwoPoint,
+        CrossoverKind::LayerSwap,
+        CrossoverKind::NeuronSwap,
+        CrossoverKind::Blend,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            CrossoverKind::Average => "Average",
+            CrossoverKind::Uniform => "Uniform",
+            CrossoverKind::SinglePoint => "Single Point",
+            CrossoverKind::TwoPoint => "Two Point",
+            CrossoverKind::LayerSwap => "Layer Swap",
+            CrossoverKind::NeuronSwap => "Neuron Swap",
+            CrossoverKind::Blend => "Blend (BLX-alpha)",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initialization::WeightInit;
+    use crate::neural_network::{Activation, LayerParameters, Topology};
+
+    // One operator of every kind
+    fn operators() -> Vec<Box<dyn CrossoverOperator>> {
+        vec![
+            Box::new(AverageCrossover),
+            Box::new(UniformCrossover),
+            Box::new(SinglePointCrossover),
+            Box::new(TwoPointCrossover),
+            Box::new(LayerSwapCrossover),
+            Box::new(NeuronSwapCrossover),
+            Box::new(BlendCrossover { alpha: 0.5 }),
+        ]
+    }
+
+    fn network(layer_sizes: &[usize], recurrent: &[bool], rng: &mut StdRng) -> NeuralNetwork {
+        let topology = Topology::new(layer_sizes, &[Activation::Tanh; 3], recurrent);
+        NeuralNetwork::from_topology(&topology, WeightInit::Xavier, rng)
+    }
+
+    #[test]
+    fn parents_of_different_shapes_are_rejected() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let small = network(&[4, 3, 1], &[], &mut rng);
+        let large = network(&[4, 6, 1], &[], &mut rng);
+        let recurrent = network(&[4, 3, 1], &[true], &mut rng);
+        for operator in operators() {
+            for (parent1, parent2) in [(&small, &large), (&large, &small), (&small, &recurrent)] {
+                assert!(matches!(
+                    operator.crossover(parent1, parent2, &mut rng),
+                    Err(NetworkError::TopologyMismatch { .. })
+                ));
+            }
+        }
+    }
+
+    // Two parents of the same recurrent shape that differ in every gene
+    fn parents(rng: &mut StdRng) -> (NeuralNetwork, NeuralNetwork) {
+        let parent1 = network(&[4, 5, 3, 1], &[true, false, true], rng);
+        let mut parent2 = network(&[4, 5, 3, 1], &[true, false, true], rng);
+        for gene in parent2.genes_mut() {
+            *gene += 10.0;
+        }
+        (parent1, parent2)
+    }
+
+    // For every gene, whether the child took it from parent1, parent2 or neither
+    fn gene_origins(
+        child: &NeuralNetwork,
+        parent1: &NeuralNetwork,
+        parent2: &NeuralNetwork,
+    ) -> Vec<u8> {
+        child
+            .genes()
+            .zip(parent1.genes().zip(parent2.genes()))
+            .map(|(c, (p1, p2))| {
+                if c == p1 {
+                    1
+                } else if c == p2 {
+                    2
+                } else {
+                    0
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identical_parents_have_an_identical_child() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (parent, _) = parents(&mut rng);
+        for operator in operators() {
+            assert_eq!(
+                operator.crossover(&parent, &parent, &mut rng).unwrap(),
+                parent
+            );
+        }
+    }
+
+    #[test]
+    fn average_takes_the_midpoint() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (parent1, parent2) = parents(&mut rng);
+        let child = AverageCrossover
+            .crossover(&parent1, &parent2, &mut rng)
+            .unwrap();
+        for (c, (p1, p2)) in child.genes().zip(parent1.genes().zip(parent2.genes())) {
+            assert_eq!(*c, (p1 + p2) / 2.0);
+        }
+    }
+
+    #[test]
+    fn uniform_mixes_genes_of_both_parents() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let (parent1, parent2) = parents(&mut rng);
+        let child = UniformCrossover
+            .crossover(&parent1, &parent2, &mut rng)
+            .unwrap();
+        let origins = gene_origins(&child, &parent1, &parent2);
+        assert!(!origins.contains(&0));
+        assert!(origins.contains(&1) && origins.contains(&2));
+    }
+
+    #[test]
+    fn point_crossovers_take_runs_of_genes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let (parent1, parent2) = parents(&mut rng);
+        for _ in 0..20 {
+            // parent1 up to the cut, parent2 after it
+            let child = SinglePointCrossover
+                .crossover(&parent1, &parent2, &mut rng)
+                .unwrap();
+            let origins = gene_origins(&child, &parent1, &parent2);
+            let cut = origins.iter().take_while(|origin| **origin == 1).count();
+            assert!(origins[cut..].iter().all(|origin| *origin == 2));
+
+            // parent2 only between the two cuts
+            let child = TwoPointCrossover
+                .crossover(&parent1, &parent2, &mut rng)
+                .unwrap();
+            let origins = gene_origins(&child, &parent1, &parent2);
+            let start = origins.iter().take_while(|origin| **origin == 1).count();
+            let end = start
+                + origins[start..]
+                    .iter()
+                    .take_while(|origin| **origin == 2)
+                    .count();
+            assert!(origins[end..].iter().all(|origin| *origin == 1));
+        }
+    }
+
+    #[test]
+    fn swaps_keep_layers_and_neurons_whole() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let (parent1, parent2) = parents(&mut rng);
+        // Which parent every row of weights, row of recurrent weights and bias matches
+        let sources = |child: &NeuralNetwork| -> Vec<Vec<u8>> {
+            child
+                .layer_parameters()
+                .zip(parent1.layer_parameters().zip(parent2.layer_parameters()))
+                .map(|(c, (p1, p2))| {
+                    (0..c.biases.len())
+                        .map(|neuron| {
+                            let neuron_of = |layer: &LayerParameters| {
+                                (
+                                    layer.weights[neuron].clone(),
+                                    layer.recurrent_weights.get(neuron).cloned(),
+                                    layer.biases[neuron],
+                                )
+                            };
+                            let c = neuron_of(&c);
+                            if c == neuron_of(&p1) {
+                                1
+                            } else if c == neuron_of(&p2) {
+                                2
+                            } else {
+                                0
+                            }
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        let (mut layer_mix, mut neuron_mix) = (false, false);
+        for _ in 0..20 {
+            let child = LayerSwapCrossover
+                .crossover(&parent1, &parent2, &mut rng)
+                .unwrap();
+            let layers = sources(&child);
+            for neurons in &layers {
+                assert!(neurons
+                    .iter()
+                    .all(|source| *source == neurons[0] && *source != 0));
+            }
+            layer_mix |= layers.iter().any(|neurons| neurons[0] != layers[0][0]);
+
+            let child = NeuronSwapCrossover
+                .crossover(&parent1, &parent2, &mut rng)
+                .unwrap();
+            let layers = sources(&child);
+            assert!(layers.iter().flatten().all(|source| *source != 0));
+            neuron_mix |= layers
+                .iter()
+                .any(|neurons| neurons.iter().any(|s| *s != neurons[0]));
+        }
+        assert!(layer_mix && neuron_mix);
+    }
+
+    #[test]
+    fn blend_stays_within_the_widened_range() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (parent1, parent2) = parents(&mut rng);
+        for alpha in [0.0, 0.5] {
+            let child = BlendCrossover { alpha }
+                .crossover(&parent1, &parent2, &mut rng)
+                .unwrap();
+            for (c, (p1, p2)) in child.genes().zip(parent1.genes().zip(parent2.genes())) {
+                let extension = alpha * (p1 - p2).abs();
+                assert!(*c >= p1.min(*p2) - extension && *c <= p1.max(*p2) + extension);
+            }
+        }
+    }
+}
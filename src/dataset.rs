@@ -0,0 +1,128 @@
+// Recorded (observation, jump) pairs for supervised training. Stored as CSV with a
+// header line and one sample per line, so datasets can be inspected and edited by hand.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const DATASET_HEADER: &str = "bird_y,velocity,horizontal_distance,vertical_gap_position,jump";
+
+// One step of a flight: the inputs the network sees and whether the bird jumped
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Demonstration {
+    pub inputs: [f32; 4], // See Observation::as_inputs
+    pub jump: bool,
+}
+
+impl Demonstration {
+    // Output the network should produce
+    pub fn target(&self) -> f32 {
+        if self.jump {
+            1.0
+        } else {
+            0.0
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DatasetError {
+    Io(io::Error),
+    Malformed { line: usize, reason: String },
+    Empty,
+}
+
+impl fmt::Display for DatasetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DatasetError::Io(err) => write!(f, "could not access dataset file: {}", err),
+            DatasetError::Malformed { line, reason } => {
+                write!(f, "malformed dataset line {}: {}", line, reason)
+            }
+            DatasetError::Empty => write!(f, "dataset has no samples"),
+        }
+    }
+}
+
+impl std::error::Error for DatasetError {}
+
+impl From<io::Error> for DatasetError {
+    fn from(err: io::Error) -> Self {
+        DatasetError::Io(err)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Dataset {
+    pub samples: Vec<Demonstration>,
+}
+
+impl Dataset {
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn load(path: &Path) -> Result<Self, DatasetError> {
+        let text = fs::read_to_string(path)?;
+        let mut samples = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line == DATASET_HEADER {
+                continue;
+            }
+            let malformed = |reason: String| DatasetError::Malformed {
+                line: index + 1,
+                reason,
+            };
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() != 5 {
+                return Err(malformed(format!(
+                    "expected 5 fields, found {}",
+                    fields.len()
+                )));
+            }
+            let mut inputs = [0.0; 4];
+            for (input, field) in inputs.iter_mut().zip(&fields) {
+                *input = field
+                    .parse()
+                    .map_err(|_| malformed(format!("'{}' is not a number", field)))?;
+            }
+            let jump = match fields[4] {
+                "1" => true,
+                "0" => false,
+                other => return Err(malformed(format!("jump must be 0 or 1, found '{}'", other))),
+            };
+            samples.push(Demonstration { inputs, jump });
+        }
+        if samples.is_empty() {
+            return Err(DatasetError::Empty);
+        }
+        Ok(Self { samples })
+    }
+
+    // Add samples to the end of a dataset file, which is created with a header if needed
+    pub fn append(path: &Path, samples: &[Demonstration]) -> Result<(), DatasetError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        let mut text = String::new();
+        if file.metadata()?.len() == 0 {
+            text.push_str(DATASET_HEADER);
+            text.push('\n');
+        }
+        for sample in samples {
+            let [bird_y, velocity, horizontal_distance, vertical_gap_position] = sample.inputs;
+            text.push_str(&format!(
+                "{},{},{},{},{}\n",
+                bird_y,
+                velocity,
+                horizontal_distance,
+                vertical_gap_position,
+                u8::from(sample.jump)
+            ));
+        }
+        file.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+// Differential evolution (Storn and Price 1997). Every bird owns a target genome. Each
+// generation flies one trial genome per target, mixed from the difference of other
+// targets, and a trial replaces its target if it does at least as well.
+use rand::prelude::*;
+
+use crate::gui::GuiParameters;
+use crate::initialization::WeightInit;
+use crate::neural_network::NeuralNetwork;
+use crate::optimizer::ParameterOptimizer;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeStrategy {
+    #[default]
+    Rand1Bin, // Mutant around a random target
+    Best1Bin, // Mutant around the fittest target
+}
+
+impl DeStrategy {
+    pub const ALL: [DeStrategy; 2] = [DeStrategy::Rand1Bin, DeStrategy::Best1Bin];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DeStrategy::Rand1Bin => "DE/rand/1/bin",
+            DeStrategy::Best1Bin => "DE/best/1/bin",
+        }
+    }
+}
+
+pub struct DifferentialEvolution {
+    strategy: DeStrategy,
+    weight: f32,    // F, scale of the difference vector
+    crossover: f32, // CR, chance of taking each gene from the mutant
+    targets: Vec<Vec<f32>>,
+    fitness: Vec<f32>,     // Of each target, empty until the targets have flown
+    trials: Vec<Vec<f32>>, // Genomes of the last ask
+    replacements: usize,   // Targets replaced by the last generation of trials
+}
+
+impl DifferentialEvolution {
+    // The first target is start, the others are fresh networks of the same topology drawn
+    // with weight_init
+    pub fn new(
+        start: &NeuralNetwork,
+        weight_init: WeightInit,
+        strategy: DeStrategy,
+        weight: f32,
+        crossover: f32,
+        population_size: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let topology = start.topology();
+        let targets = std::iter::once(start.to_genome())
+            .chain(
+                (1..population_size.max(4))
+                    .map(|_| NeuralNetwork::from_topology(&topology, weight_init, rng).to_genome()),
+            )
+            .collect();
+        Self {
+            strategy,
+            weight,
+            crossover,
+            targets,
+            fitness: Vec::new(),
+            trials: Vec::new(),
+            replacements: 0,
+        }
+    }
+
+    // Up to three distinct targets other than target
+    fn pick_others(&self, target: usize, rng: &mut StdRng) -> Vec<usize> {
+        let candidates: Vec<usize> = (0..self.targets.len())
+            .filter(|other| *other != target)
+            .collect();
+        candidates.choose_multiple(rng, 3).copied().collect()
+    }
+
+    fn trial(&self, target: usize, rng: &mut StdRng) -> Vec<f32> {
+        let others = self.pick_others(target, rng);
+        let base = match self.strategy {
+            DeStrategy::Rand1Bin => others[0],
+            DeStrategy::Best1Bin => (0..self.fitness.len())
+                .max_by(|a, b| self.fitness[*a].total_cmp(&self.fitness[*b]))
+                .unwrap_or(others[0]),
+        };
+        let (a, b) = (others[1], others[2]);
+        let genes = self.targets[target].len();
+        let forced = rng.gen_range(0..genes.max(1)); // At least one gene comes from the mutant
+        (0..genes)
+            .map(|gene| {
+                if gene == forced || rng.gen::<f32>() < self.crossover {
+                    self.targets[base][gene]
+                        + self.weight * (self.targets[a][gene] - self.targets[b][gene])
+                } else {
+                    self.targets[target][gene]
+                }
+            })
+            .collect()
+    }
+}
+
+impl ParameterOptimizer for DifferentialEvolution {
+    // The targets themselves until they have a fitness, then one trial per target
+    fn ask(&mut self, rng: &mut StdRng) -> Vec<Vec<f32>> {
+        self.trials = if self.fitness.is_empty() {
+            self.targets.clone()
+        } else {
+            (0..self.targets.len())
+                .map(|target| self.trial(target, rng))
+                .collect()
+        };
+        self.trials.clone()
+    }
+
+    fn tell(&mut self, fitness: &[f32]) {
+        if fitness.len() != self.targets.len() || self.trials.len() != self.targets.len() {
+            return;
+        }
+        if self.fitness.is_empty() {
+            self.fitness = fitness.to_vec();
+            return;
+        }
+        self.replacements = 0;
+        for (target, trial) in self.trials.drain(..).enumerate() {
+            if fitness[target] >= self.fitness[target] {
+                self.targets[target] = trial;
+                self.fitness[target] = fitness[target];
+                self.replacements += 1;
+            }
+        }
+    }
+
+    // F, CR and the strategy apply from the next generation on
+    fn configure(&mut self, params: &GuiParameters) {
+        self.strategy = params.de_strategy;
+        self.weight = params.de_weight;
+        self.crossover = params.de_crossover;
+    }
+
+    fn status(&self) -> String {
+        let best = self.fitness.iter().copied().fold(f32::NAN, f32::max);
+        format!(
+            "{} | best target fitness {:.2} | {} targets replaced",
+            self.strategy.name(),
+            best,
+            self.replacements
+        )
+    }
+}
@@ -0,0 +1,314 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::hall_of_fame::*;
+use crate::neural_network::*;
+
+// Bump when the layout of GenomeFile changes. Older versions must stay loadable.
+// 1: one activation for the whole network, which was always Sigmoid
+// 2: activation stored per layer
+// 3: generation of the genome, hall of fame file
+// 4: recurrent weights per layer
+// 5: weight initialization scheme of the network
+pub const GENOME_FORMAT_VERSION: u32 = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GenomeFormat {
+    Json,   // Human-readable
+    Binary, // Compact MessagePack
+}
+
+impl GenomeFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            GenomeFormat::Json => "json",
+            GenomeFormat::Binary => "bin",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GenomeError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    Binary(String),
+    UnsupportedVersion(u32),
+    ShapeMismatch {
+        expected: Vec<usize>,
+        found: Vec<usize>,
+    },
+    Malformed(String),
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GenomeError::Io(err) => write!(f, "could not access genome file: {}", err),
+            GenomeError::Json(err) => write!(f, "invalid JSON genome: {}", err),
+            GenomeError::Binary(err) => write!(f, "invalid binary genome: {}", err),
+            GenomeError::UnsupportedVersion(version) => write!(
+                f,
+                "genome format version {} is newer than supported version {}",
+                version, GENOME_FORMAT_VERSION
+            ),
+            GenomeError::ShapeMismatch { expected, found } => write!(
+                f,
+                "genome has layer sizes {:?} but the network size is {:?}",
+                found, expected
+            ),
+            GenomeError::Malformed(reason) => write!(f, "malformed genome: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for GenomeError {}
+
+impl From<io::Error> for GenomeError {
+    fn from(err: io::Error) -> Self {
+        GenomeError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for GenomeError {
+    fn from(err: serde_json::Error) -> Self {
+        GenomeError::Json(err)
+    }
+}
+
+impl From<rmp_serde::encode::Error> for GenomeError {
+    fn from(err: rmp_serde::encode::Error) -> Self {
+        GenomeError::Binary(err.to_string())
+    }
+}
+
+impl From<rmp_serde::decode::Error> for GenomeError {
+    fn from(err: rmp_serde::decode::Error) -> Self {
+        GenomeError::Binary(err.to_string())
+    }
+}
+
+// On-disk representation of a single genome
+#[derive(Serialize, Deserialize)]
+struct GenomeFile {
+    version: u32,
+    layer_sizes: Vec<usize>,
+    #[serde(default)]
+    generation: usize,
+    #[serde(default)]
+    fitness: f32,
+    #[serde(default)]
+    score: f32,
+    network: NeuralNetwork,
+}
+
+// On-disk representation of a hall of fame, best genome first
+#[derive(Serialize, Deserialize)]
+struct HallOfFameFile {
+    version: u32,
+    genomes: Vec<GenomeFile>,
+}
+
+impl GenomeFile {
+    fn new(entry: &HallOfFameEntry) -> Self {
+        Self {
+            version: GENOME_FORMAT_VERSION,
+            layer_sizes: entry.neural_network.layer_sizes(),
+            generation: entry.generation,
+            fitness: entry.fitness,
+            score: entry.score,
+            network: entry.neural_network.clone(),
+        }
+    }
+
+    // Check that the genome fits a network of the given layer sizes
+    fn into_entry(self, expected_sizes: &[usize]) -> Result<HallOfFameEntry, GenomeError> {
+        if self.version > GENOME_FORMAT_VERSION {
+            return Err(GenomeError::UnsupportedVersion(self.version));
+        }
+        if self.layer_sizes != expected_sizes {
+            return Err(GenomeError::ShapeMismatch {
+                expected: expected_sizes.to_vec(),
+                found: self.layer_sizes,
+            });
+        }
+        if self.network.layer_sizes() != self.layer_sizes || !self.network.is_consistent() {
+            return Err(GenomeError::Malformed(format!(
+                "weights do not match the recorded layer sizes {:?}",
+                self.layer_sizes
+            )));
+        }
+        Ok(HallOfFameEntry {
+            neural_network: self.network,
+            generation: self.generation,
+            fitness: self.fitness,
+            score: self.score,
+        })
+    }
+}
+
+fn write_file(path: &Path, format: GenomeFormat, file: &impl Serialize) -> Result<(), GenomeError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let bytes = match format {
+        GenomeFormat::Json => serde_json::to_vec_pretty(file)?,
+        GenomeFormat::Binary => rmp_serde::to_vec_named(file)?,
+    };
+    fs::write(path, bytes)?;
+    Ok(())
+}
+
+fn read_file<T: DeserializeOwned>(path: &Path, format: GenomeFormat) -> Result<T, GenomeError> {
+    let bytes = fs::read(path)?;
+    Ok(match format {
+        GenomeFormat::Json => serde_json::from_slice(&bytes)?,
+        GenomeFormat::Binary => rmp_serde::from_slice(&bytes)?,
+    })
+}
+
+pub fn save_genome(
+    path: &Path,
+    format: GenomeFormat,
+    entry: &HallOfFameEntry,
+) -> Result<(), GenomeError> {
+    write_file(path, format, &GenomeFile::new(entry))
+}
+
+// Load a genome and check that it fits a network of the given layer sizes
+pub fn load_genome(
+    path: &Path,
+    format: GenomeFormat,
+    expected_sizes: &[usize],
+) -> Result<HallOfFameEntry, GenomeError> {
+    read_file::<GenomeFile>(path, format)?.into_entry(expected_sizes)
+}
+
+fn genome_path(directory: &str, name: &str, format: GenomeFormat) -> PathBuf {
+    Path::new(directory).join(format!("{}.{}", name, format.extension()))
+}
+
+// Writes the whole hall of fame, and the best genome on its own so it can be loaded
+// with load_genome
+pub fn save_hall_of_fame(
+    hall_of_fame: &HallOfFame,
+    directory: &str,
+    format: GenomeFormat,
+) -> Result<(), GenomeError> {
+    let file = HallOfFameFile {
+        version: GENOME_FORMAT_VERSION,
+        genomes: hall_of_fame.entries().iter().map(GenomeFile::new).collect(),
+    };
+    write_file(
+        &genome_path(directory, "hall_of_fame", format),
+        format,
+        &file,
+    )?;
+    match hall_of_fame.best() {
+        Some(best) => save_genome(&genome_path(directory, "best", format), format, best),
+        None => Ok(()),
+    }
+}
+
+// Every genome is read before the hall of fame is touched, so a failed load leaves it
+// unchanged. Directories saved before the hall of fame existed hold a best and a
+// second best genome instead.
+pub fn load_hall_of_fame(
+    hall_of_fame: &mut HallOfFame,
+    directory: &str,
+    format: GenomeFormat,
+    expected_sizes: &[usize],
+) -> Result<(), GenomeError> {
+    let path = genome_path(directory, "hall_of_fame", format);
+    let entries = if path.exists() {
+        let file: HallOfFameFile = read_file(&path, format)?;
+        if file.version > GENOME_FORMAT_VERSION {
+            return Err(GenomeError::UnsupportedVersion(file.version));
+        }
+        file.genomes
+            .into_iter()
+            .map(|genome| genome.into_entry(expected_sizes))
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![
+            load_genome(
+                &genome_path(directory, "best", format),
+                format,
+                expected_sizes,
+            )?,
+            load_genome(
+                &genome_path(directory, "second_best", format),
+                format,
+                expected_sizes,
+            )?,
+        ]
+    };
+    hall_of_fame.replace(entries);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::prelude::*;
+
+    use super::*;
+    use crate::initialization::WeightInit;
+
+    const SIZES: [usize; 3] = [4, 3, 1];
+
+    fn entry() -> HallOfFameEntry {
+        let mut rng = StdRng::seed_from_u64(9);
+        let topology = Topology::new(&SIZES, &[Activation::Tanh, Activation::Sigmoid], &[true]);
+        HallOfFameEntry {
+            neural_network: NeuralNetwork::from_topology(&topology, WeightInit::He, &mut rng),
+            generation: 12,
+            fitness: 34.5,
+            score: 6.0,
+        }
+    }
+
+    // A fresh directory under the system temp directory for one test
+    fn scratch_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("dont_crash_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        directory
+    }
+
+    #[test]
+    fn genome_round_trips_in_both_formats() {
+        let directory = scratch_directory("round_trip");
+        let saved = entry();
+        for format in [GenomeFormat::Json, GenomeFormat::Binary] {
+            let path = genome_path(directory.to_str().unwrap(), "genome", format);
+            save_genome(&path, format, &saved).unwrap();
+            let loaded = load_genome(&path, format, &SIZES).unwrap();
+            assert_eq!(loaded.neural_network, saved.neural_network);
+            assert_eq!(loaded.neural_network.weight_init(), WeightInit::He);
+            assert_eq!(
+                (loaded.generation, loaded.fitness, loaded.score),
+                (saved.generation, saved.fitness, saved.score)
+            );
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn genome_of_another_network_size_is_rejected() {
+        let directory = scratch_directory("shape_mismatch");
+        let path = genome_path(directory.to_str().unwrap(), "genome", GenomeFormat::Json);
+        save_genome(&path, GenomeFormat::Json, &entry()).unwrap();
+        match load_genome(&path, GenomeFormat::Json, &[4, 5, 1]) {
+            Err(GenomeError::ShapeMismatch { expected, found }) => {
+                assert_eq!(expected, vec![4, 5, 1]);
+                assert_eq!(found, SIZES.to_vec());
+            }
+            other => panic!("expected a shape mismatch, got {:?}", other.map(|_| ())),
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
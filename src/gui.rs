@@ -3,74 +3,215 @@ use bevy_egui::{
     egui::{self, Color32, RichText},
     EguiContexts,
 };
+use rand::Rng;
 
-use crate::neural_network::NeuralNetwork;
+use crate::cma_es::CmaEs;
+use crate::components::{BrainKind, HumanPlay};
+use crate::crossover::*;
+use crate::differential_evolution::{DeStrategy, DifferentialEvolution};
+use crate::genome_io::*;
+use crate::hall_of_fame::HallOfFame;
+use crate::initialization::WeightInit;
+use crate::mutation::*;
+use crate::neat::NeatPopulation;
+use crate::network_size;
+use crate::neural_network::{Activation, NeuralNetwork, Topology};
+use crate::novelty::{NoveltyArchive, SelectionObjective};
+use crate::openai_es::{GradientOptimizer, OpenAiEs};
+use crate::optimizer::{ParameterOptimizer, ParameterSearch, TrainingAlgorithm};
+use crate::pareto::ParetoFront;
+use crate::particle_swarm::{ParticleSwarm, SwarmTopology};
+use crate::random::TrainingRng;
+use crate::selection::SelectionStrategy;
+use crate::supervised::{Loss, SupervisedTraining};
 
-pub const WINDOW_WIDTH: f32 = 1280.0;
-pub const WINDOW_HEIGHT: f32 = 720.0;
-pub const GAP_WIDTH: f32 = 125.0;
-pub const PIPE_VELOCITY: f32 = 200.0;
-pub const PIPE_WIDTH: f32 = 50.0;
-pub const BIRD_SIZE: f32 = 15.0;
-pub const SPAWN_X_POINT: f32 = -300.0;
 pub const POPULATION_SIZE: usize = 400;
 
 #[derive(Clone, Debug, Resource)]
 pub struct GuiParameters {
-    pub force_scaling: f32,
     pub passed_time_since_start: f32,
-    pub passed_time_since_last_pipe: f32,
-    pub population_size: usize,    // Number of birds in population
-    pub dead_bird_count: usize,    // Check if all birds are dead
-    pub current_score: f32,        // For display
+    pub population_size: usize,        // Number of birds in population
+    pub dead_bird_count: usize,        // Check if all birds are dead
+    pub current_score: f32,            // For display
     pub generation_dead: bool, // True if entire population is dead. Then a new population can be spawned
     pub mutation_rate: f32,    // Rate of mutation (factor to scale with, positive or negative)
-    pub mutation_probability: f32, // Probability of mutation happening to weight
+    pub mutation_probability: f32, // Probability of mutation happening to each weight and bias
+    pub mutation_kind: MutationKind, // Operator applied to a mutating weight or bias
+    pub mutation_sigma: f32,   // Standard deviation of Gaussian mutation
+    pub mutation_reset_range: f32, // Random reset draws new values in -range..range
+    pub crossover_kind: CrossoverKind, // How two parents are recombined
+    pub crossover_alpha: f32,  // Widening of the parent range in blend crossover
     pub current_generation: usize,
     pub number_of_visible_bird: usize,
     pub start_training: bool,
+    pub genome_directory: String, // Where the hall of fame is saved to and loaded from
+    pub genome_format: GenomeFormat,
+    pub layer_activations: Vec<Activation>, // Activation of each layer of new networks
+    pub recurrent_layers: Vec<bool>,        // Elman layers that also see their previous outputs
+    pub weight_init: WeightInit,            // How the weights and biases of new networks are drawn
+    pub supervised: SupervisedTraining,     // Backpropagation settings of pre-training
+    pub dataset_path: String,               // Demonstrations the population is pre-trained on
+    pub pretrain_requested: bool,           // Pre-train a new population before the next step
+    pub seed_input: String,                 // Seed typed into the GUI, the current one if empty
+    pub restart_seed: Option<u64>, // Start the run over with this seed before the next step
+    pub selection_strategy: SelectionStrategy, // How parents are picked from the population
+    pub selection_objective: SelectionObjective, // What parents are selected by
+    pub novelty_weight: f32,       // Share of novelty in the blended objective
+    pub elite_count: usize, // Best genomes of the hall of fame copied unchanged into each generation
+    pub brain_kind: BrainKind, // Takes effect with the next generation
+    pub training_algorithm: TrainingAlgorithm, // How fixed networks are trained
+    pub cma_sigma: f32,     // Initial step size of CMA-ES
+    pub es_sigma: f32,      // Noise standard deviation of OpenAI-ES
+    pub es_learning_rate: f32,
+    pub es_momentum: f32, // Of SGD in OpenAI-ES
+    pub es_optimizer: GradientOptimizer,
+    pub de_strategy: DeStrategy,
+    pub de_weight: f32,    // F of differential evolution
+    pub de_crossover: f32, // CR of differential evolution
+    pub swarm_topology: SwarmTopology,
+    pub swarm_inertia: f32,
+    pub swarm_cognitive: f32, // Pull of a particle towards its own best genome
+    pub swarm_social: f32,    // Pull of a particle towards the best genome of its neighbourhood
+    pub neat_compatibility_threshold: f32, // Largest distance between genomes of one species
+    pub neat_add_node_probability: f32,
+    pub neat_add_connection_probability: f32,
 }
 
 impl Default for GuiParameters {
     fn default() -> Self {
         Self {
-            force_scaling: 45.0,
             passed_time_since_start: 0.0,
-            passed_time_since_last_pipe: 10000.0,
             population_size: POPULATION_SIZE,
             dead_bird_count: 0,
             current_score: 0.0,
             generation_dead: false,
             mutation_rate: 0.125,
             mutation_probability: 0.5,
+            mutation_kind: MutationKind::Uniform,
+            mutation_sigma: 0.1,
+            mutation_reset_range: 1.0,
+            crossover_kind: CrossoverKind::Average,
+            crossover_alpha: 0.5,
             current_generation: 0,
             number_of_visible_bird: POPULATION_SIZE,
             start_training: false,
+            genome_directory: "best_birds".to_string(),
+            genome_format: GenomeFormat::Json,
+            layer_activations: vec![Activation::Sigmoid; network_size.len() - 1],
+            recurrent_layers: vec![false; network_size.len() - 1],
+            weight_init: WeightInit::default(),
+            supervised: SupervisedTraining::default(),
+            dataset_path: "demonstrations.csv".to_string(),
+            pretrain_requested: false,
+            seed_input: String::new(),
+            restart_seed: None,
+            selection_strategy: SelectionStrategy::default(),
+            selection_objective: SelectionObjective::Fitness,
+            novelty_weight: 0.5,
+            elite_count: 2,
+            brain_kind: BrainKind::Network,
+            training_algorithm: TrainingAlgorithm::Genetic,
+            cma_sigma: 0.5,
+            es_sigma: 0.1,
+            es_learning_rate: 0.03,
+            es_momentum: 0.9,
+            es_optimizer: GradientOptimizer::default(),
+            de_strategy: DeStrategy::default(),
+            de_weight: 0.5,
+            de_crossover: 0.9,
+            swarm_topology: SwarmTopology::default(),
+            swarm_inertia: 0.729,
+            swarm_cognitive: 1.494,
+            swarm_social: 1.494,
+            neat_compatibility_threshold: 3.0,
+            neat_add_node_probability: 0.03,
+            neat_add_connection_probability: 0.05,
         }
     }
 }
 
-#[derive(Clone, Debug, Resource)]
-pub struct BestBirds {
-    pub best_neural_network: NeuralNetwork,
-    pub second_best_neural_network: NeuralNetwork,
-    pub best_score: f32,
-    pub second_best_score: f32,
-    pub best_fitness: f32,
-    pub second_best_fitness: f32,
-}
+impl GuiParameters {
+    // Architecture of new networks
+    pub fn topology(&self) -> Topology {
+        Topology::new(
+            &network_size,
+            &self.layer_activations,
+            &self.recurrent_layers,
+        )
+    }
 
-#[derive(Clone, Debug, Component)]
-pub struct Environment {
-    pub horizontal_distance: f32,
-    pub vertical_gap_position: f32,
-}
+    // The selected mutation operator with its current setting
+    pub fn mutation_operator(&self) -> Box<dyn MutationOperator> {
+        match self.mutation_kind {
+            MutationKind::Uniform => Box::new(UniformPerturbation {
+                rate: self.mutation_rate,
+            }),
+            MutationKind::Gaussian => Box::new(GaussianPerturbation {
+                sigma: self.mutation_sigma,
+            }),
+            MutationKind::Reset => Box::new(RandomReset {
+                range: self.mutation_reset_range,
+            }),
+            MutationKind::SignFlip => Box::new(SignFlip),
+        }
+    }
 
-impl Default for Environment {
-    fn default() -> Self {
-        Self {
-            horizontal_distance: 0.0,
-            vertical_gap_position: 0.0,
+    // The selected crossover operator with its current setting
+    pub fn crossover_operator(&self) -> Box<dyn CrossoverOperator> {
+        match self.crossover_kind {
+            CrossoverKind::Average => Box::new(AverageCrossover),
+            CrossoverKind::Uniform => Box::new(UniformCrossover),
+            CrossoverKind::SinglePoint => Box::new(SinglePointCrossover),
+            CrossoverKind::TwoPoint => Box::new(TwoPointCrossover),
+            CrossoverKind::LayerSwap => Box::new(LayerSwapCrossover),
+            CrossoverKind::NeuronSwap => Box::new(NeuronSwapCrossover),
+            CrossoverKind::Blend => Box::new(BlendCrossover {
+                alpha: self.crossover_alpha,
+            }),
+        }
+    }
+
+    // A new optimizer of the selected algorithm starting around the start network, or None
+    // for the genetic algorithm, which breeds networks directly
+    pub fn parameter_optimizer(
+        &self,
+        start: &NeuralNetwork,
+        rng: &mut impl Rng,
+    ) -> Option<Box<dyn ParameterOptimizer>> {
+        match self.training_algorithm {
+            TrainingAlgorithm::Genetic => None,
+            TrainingAlgorithm::CmaEs => Some(Box::new(CmaEs::new(
+                &start.to_genome(),
+                self.cma_sigma,
+                self.population_size,
+            ))),
+            TrainingAlgorithm::OpenAiEs => Some(Box::new(OpenAiEs::new(
+                &start.to_genome(),
+                self.es_sigma,
+                self.es_learning_rate,
+                self.es_momentum,
+                self.es_optimizer,
+                self.population_size,
+            ))),
+            TrainingAlgorithm::DifferentialEvolution => Some(Box::new(DifferentialEvolution::new(
+                start,
+                self.weight_init,
+                self.de_strategy,
+                self.de_weight,
+                self.de_crossover,
+                self.population_size,
+                rng,
+            ))),
+            TrainingAlgorithm::ParticleSwarm => Some(Box::new(ParticleSwarm::new(
+                start,
+                self.weight_init,
+                self.swarm_topology,
+                self.swarm_inertia,
+                self.swarm_cognitive,
+                self.swarm_social,
+                self.population_size,
+                rng,
+            ))),
         }
     }
 }
@@ -78,12 +219,40 @@ impl Default for Environment {
 pub fn update_gui(
     mut egui_ctx: EguiContexts,
     mut gui_parameters: ResMut<GuiParameters>,
-    best_birds: ResMut<BestBirds>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+    neat: Res<NeatPopulation>,
+    search: Res<ParameterSearch>,
+    (archive, front, human): (Res<NoveltyArchive>, Res<ParetoFront>, Res<HumanPlay>),
+    rng: Res<TrainingRng>,
 ) {
     egui::Window::new("Parameters").show(egui_ctx.ctx_mut(), |ui| {
         if ui.button("Start training").clicked() {
             gui_parameters.start_training = !gui_parameters.start_training;
         }
+        // A run is reproduced by restarting it with the same seed and settings
+        ui.horizontal(|ui| {
+            ui.label(format!("Seed: {}", rng.seed));
+            ui.add(
+                egui::TextEdit::singleline(&mut gui_parameters.seed_input)
+                    .hint_text("new seed")
+                    .desired_width(160.0),
+            );
+            let restart = ui.button("Restart").on_hover_text(
+                "Start over from generation 0 with the entered seed, or the current one if empty. \
+                 Clears the hall of fame, so save it first to keep it.",
+            );
+            if restart.clicked() {
+                let input = gui_parameters.seed_input.trim();
+                if input.is_empty() {
+                    gui_parameters.restart_seed = Some(rng.seed);
+                } else {
+                    match input.parse() {
+                        Ok(seed) => gui_parameters.restart_seed = Some(seed),
+                        Err(_) => println!("Seed must be a whole number, found '{}'", input),
+                    }
+                }
+            }
+        });
         ui.label(format!(
             "Time since start: {:.2}",
             gui_parameters.passed_time_since_start
@@ -92,16 +261,9 @@ pub fn update_gui(
             ui.label("Population Size");
             ui.add(egui::Slider::new(
                 &mut gui_parameters.population_size,
-                1..=400,
+                1..=10_000,
             ));
         });
-        // ui.horizontal(|ui| {
-        //     ui.label("Force Scaling");
-        //     ui.add(egui::Slider::new(
-        //         &mut gui_parameters.force_scaling,
-        //         1.0..=50.0,
-        //     ));
-        // });
 
         // set population size
         ui.label(
@@ -116,12 +278,21 @@ pub fn update_gui(
             gui_parameters.current_generation
         ));
         ui.label(
-            RichText::new(format!("Best Score: {:.2}", best_birds.best_score).to_string())
-                .color(Color32::GREEN),
+            RichText::new(
+                format!(
+                    "Best Score: {:.2}",
+                    hall_of_fame.best_score().max(neat.best_score)
+                )
+                .to_string(),
+            )
+            .color(Color32::GREEN),
         );
         ui.label(
-            RichText::new(format!("Best Fitness: {:.2}", best_birds.best_fitness))
-                .color(Color32::GREEN),
+            RichText::new(format!(
+                "Best Fitness: {:.2}",
+                hall_of_fame.best_fitness().max(neat.best_fitness)
+            ))
+            .color(Color32::GREEN),
         );
         ui.label(
             RichText::new(format!(
@@ -130,14 +301,44 @@ pub fn update_gui(
             ))
             .color(Color32::YELLOW),
         );
-        // set mutation rate
-        ui.horizontal(|ui| {
-            ui.label("Mutation Rate");
-            ui.add(egui::Slider::new(
-                &mut gui_parameters.mutation_rate,
-                -1.0..=1.0,
-            ));
-        });
+        // set mutation operator and its setting
+        egui::ComboBox::from_label("Mutation")
+            .selected_text(gui_parameters.mutation_kind.name())
+            .show_ui(ui, |ui| {
+                for option in MutationKind::ALL {
+                    ui.selectable_value(&mut gui_parameters.mutation_kind, option, option.name());
+                }
+            });
+        match gui_parameters.mutation_kind {
+            MutationKind::Uniform => {
+                ui.horizontal(|ui| {
+                    ui.label("Mutation Rate");
+                    ui.add(egui::Slider::new(
+                        &mut gui_parameters.mutation_rate,
+                        -1.0..=1.0,
+                    ));
+                });
+            }
+            MutationKind::Gaussian => {
+                ui.horizontal(|ui| {
+                    ui.label("Mutation Sigma");
+                    ui.add(egui::Slider::new(
+                        &mut gui_parameters.mutation_sigma,
+                        0.0..=1.0,
+                    ));
+                });
+            }
+            MutationKind::Reset => {
+                ui.horizontal(|ui| {
+                    ui.label("Reset Range");
+                    ui.add(egui::Slider::new(
+                        &mut gui_parameters.mutation_reset_range,
+                        0.0..=2.0,
+                    ));
+                });
+            }
+            MutationKind::SignFlip => {}
+        }
         //set mutation probability
         ui.horizontal(|ui| {
             ui.label("Mutation Probability");
@@ -146,6 +347,425 @@ pub fn update_gui(
                 0.0..=1.0,
             ));
         });
+        // set brain of new birds
+        ui.separator();
+        egui::ComboBox::from_label("Brain")
+            .selected_text(gui_parameters.brain_kind.name())
+            .show_ui(ui, |ui| {
+                for option in BrainKind::ALL {
+                    ui.selectable_value(&mut gui_parameters.brain_kind, option, option.name());
+                }
+            });
+        if gui_parameters.brain_kind == BrainKind::Neat {
+            ui.horizontal(|ui| {
+                ui.label("Compatibility Threshold");
+                ui.add(egui::Slider::new(
+                    &mut gui_parameters.neat_compatibility_threshold,
+                    0.1..=10.0,
+                ));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Add Node Probability");
+                ui.add(egui::Slider::new(
+                    &mut gui_parameters.neat_add_node_probability,
+                    0.0..=1.0,
+                ));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Add Connection Probability");
+                ui.add(egui::Slider::new(
+                    &mut gui_parameters.neat_add_connection_probability,
+                    0.0..=1.0,
+                ));
+            });
+            let largest = neat
+                .genomes
+                .iter()
+                .max_by_key(|genome| genome.hidden_node_count());
+            ui.label(format!(
+                "Species: {} | Largest genome: {} hidden nodes, {} connections",
+                neat.species.len(),
+                largest.map_or(0, |genome| genome.hidden_node_count()),
+                largest.map_or(0, |genome| genome.enabled_connection_count()),
+            ));
+            ui.label("NEAT genomes do not enter the hall of fame and cannot be saved");
+        } else if gui_parameters.brain_kind == BrainKind::Human {
+            ui.label("Press Space to jump");
+            ui.label(format!(
+                "Recorded steps: {} | Saved to {} when the bird crashes",
+                human.recording.len(),
+                gui_parameters.dataset_path
+            ));
+        } else {
+            egui::ComboBox::from_label("Training Algorithm")
+                .selected_text(gui_parameters.training_algorithm.name())
+                .show_ui(ui, |ui| {
+                    for option in TrainingAlgorithm::ALL {
+                        ui.selectable_value(
+                            &mut gui_parameters.training_algorithm,
+                            option,
+                            option.name(),
+                        );
+                    }
+                });
+            match gui_parameters.training_algorithm {
+                TrainingAlgorithm::Genetic => {}
+                TrainingAlgorithm::CmaEs => {
+                    ui.horizontal(|ui| {
+                        ui.label("Initial Step Size");
+                        ui.add(
+                            egui::Slider::new(&mut gui_parameters.cma_sigma, 0.01..=2.0)
+                                .logarithmic(true),
+                        );
+                    });
+                }
+                TrainingAlgorithm::OpenAiEs => {
+                    ui.horizontal(|ui| {
+                        ui.label("Noise Sigma");
+                        ui.add(
+                            egui::Slider::new(&mut gui_parameters.es_sigma, 0.001..=1.0)
+                                .logarithmic(true),
+                        );
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Learning Rate");
+                        ui.add(
+                            egui::Slider::new(&mut gui_parameters.es_learning_rate, 0.0001..=1.0)
+                                .logarithmic(true),
+                        );
+                    });
+                    egui::ComboBox::from_label("Gradient Optimizer")
+                        .selected_text(gui_parameters.es_optimizer.name())
+                        .show_ui(ui, |ui| {
+                            for option in GradientOptimizer::ALL {
+                                ui.selectable_value(
+                                    &mut gui_parameters.es_optimizer,
+                                    option,
+                                    option.name(),
+                                );
+                            }
+                        });
+                    if gui_parameters.es_optimizer == GradientOptimizer::SgdMomentum {
+                        ui.horizontal(|ui| {
+                            ui.label("Momentum");
+                            ui.add(egui::Slider::new(
+                                &mut gui_parameters.es_momentum,
+                                0.0..=0.99,
+                            ));
+                        });
+                    }
+                }
+                TrainingAlgorithm::DifferentialEvolution => {
+                    egui::ComboBox::from_label("DE Strategy")
+                        .selected_text(gui_parameters.de_strategy.name())
+                        .show_ui(ui, |ui| {
+                            for option in DeStrategy::ALL {
+                                ui.selectable_value(
+                                    &mut gui_parameters.de_strategy,
+                                    option,
+                                    option.name(),
+                                );
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        ui.label("Differential Weight (F)");
+                        ui.add(egui::Slider::new(&mut gui_parameters.de_weight, 0.0..=2.0));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Crossover Rate (CR)");
+                        ui.add(egui::Slider::new(
+                            &mut gui_parameters.de_crossover,
+                            0.0..=1.0,
+                        ));
+                    });
+                }
+                TrainingAlgorithm::ParticleSwarm => {
+                    egui::ComboBox::from_label("Swarm Topology")
+                        .selected_text(gui_parameters.swarm_topology.name())
+                        .show_ui(ui, |ui| {
+                            for option in SwarmTopology::ALL {
+                                ui.selectable_value(
+                                    &mut gui_parameters.swarm_topology,
+                                    option,
+                                    option.name(),
+                                );
+                            }
+                        });
+                    ui.horizontal(|ui| {
+                        ui.label("Inertia");
+                        ui.add(egui::Slider::new(
+                            &mut gui_parameters.swarm_inertia,
+                            0.0..=1.0,
+                        ));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Cognitive Pull");
+                        ui.add(egui::Slider::new(
+                            &mut gui_parameters.swarm_cognitive,
+                            0.0..=4.0,
+                        ));
+                    });
+                    ui.horizontal(|ui| {
+                        ui.label("Social Pull");
+                        ui.add(egui::Slider::new(
+                            &mut gui_parameters.swarm_social,
+                            0.0..=4.0,
+                        ));
+                    });
+                }
+            }
+            if gui_parameters.training_algorithm != TrainingAlgorithm::Genetic {
+                if let Some(status) = search.status() {
+                    ui.label(status);
+                }
+            }
+        }
+        // set crossover operator
+        egui::ComboBox::from_label("Crossover")
+            .selected_text(gui_parameters.crossover_kind.name())
+            .show_ui(ui, |ui| {
+                for option in CrossoverKind::ALL {
+                    ui.selectable_value(&mut gui_parameters.crossover_kind, option, option.name());
+                }
+            });
+        if gui_parameters.crossover_kind == CrossoverKind::Blend {
+            ui.horizontal(|ui| {
+                ui.label("Blend Alpha");
+                ui.add(egui::Slider::new(
+                    &mut gui_parameters.crossover_alpha,
+                    0.0..=1.0,
+                ));
+            });
+        }
+        // set selection strategy
+        ui.separator();
+        let strategy = &mut gui_parameters.selection_strategy;
+        egui::ComboBox::from_label("Selection")
+            .selected_text(strategy.name())
+            .show_ui(ui, |ui| {
+                for option in SelectionStrategy::ALL {
+                    if ui
+                        .selectable_label(strategy.same_kind(&option), option.name())
+                        .clicked()
+                        && !strategy.same_kind(&option)
+                    {
+                        *strategy = option;
+                    }
+                }
+            });
+        match strategy {
+            SelectionStrategy::Tournament { size } => {
+                ui.horizontal(|ui| {
+                    ui.label("Tournament Size");
+                    ui.add(egui::Slider::new(size, 1..=20));
+                });
+            }
+            SelectionStrategy::Truncation { fraction } => {
+                ui.horizontal(|ui| {
+                    ui.label("Kept Fraction");
+                    ui.add(egui::Slider::new(fraction, 0.01..=1.0));
+                });
+            }
+            SelectionStrategy::Roulette | SelectionStrategy::Rank => {}
+        }
+        egui::ComboBox::from_label("Selection Objective")
+            .selected_text(gui_parameters.selection_objective.name())
+            .show_ui(ui, |ui| {
+                for option in SelectionObjective::ALL {
+                    ui.selectable_value(
+                        &mut gui_parameters.selection_objective,
+                        option,
+                        option.name(),
+                    );
+                }
+            });
+        if gui_parameters.selection_objective == SelectionObjective::Blend {
+            ui.horizontal(|ui| {
+                ui.label("Novelty Weight");
+                ui.add(egui::Slider::new(
+                    &mut gui_parameters.novelty_weight,
+                    0.0..=1.0,
+                ));
+            });
+        }
+        match gui_parameters.selection_objective {
+            SelectionObjective::Novelty | SelectionObjective::Blend => {
+                ui.label(format!("Archived behaviours: {}", archive.archived_count()));
+            }
+            SelectionObjective::Pareto => {
+                egui::CollapsingHeader::new(format!("Pareto Front ({} flights)", front.0.len()))
+                    .show(ui, |ui| {
+                        for point in &front.0 {
+                            ui.label(format!(
+                                "Fitness {:.2} | Score {:.0} | Flap rate {:.3}",
+                                point.fitness, point.score, point.flap_rate
+                            ));
+                        }
+                    });
+            }
+            SelectionObjective::Fitness => {}
+        }
+        // set elitism and hall of fame size
+        ui.horizontal(|ui| {
+            ui.label("Elites");
+            ui.add(egui::Slider::new(&mut gui_parameters.elite_count, 0..=20));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Hall of Fame Size");
+            if ui
+                .add(egui::Slider::new(&mut hall_of_fame.capacity, 1..=50))
+                .changed()
+            {
+                hall_of_fame.shrink_to_capacity();
+            }
+        });
+        egui::CollapsingHeader::new("Hall of Fame").show(ui, |ui| {
+            for (rank, entry) in hall_of_fame.entries().iter().enumerate() {
+                ui.label(format!(
+                    "{}. Generation {} | Fitness {:.2} | Score {:.0}",
+                    rank + 1,
+                    entry.generation,
+                    entry.fitness,
+                    entry.score
+                ));
+            }
+        });
+        // set activation function of each layer
+        ui.separator();
+        let gui_parameters = &mut *gui_parameters;
+        for (layer, (activation, recurrent)) in gui_parameters
+            .layer_activations
+            .iter_mut()
+            .zip(gui_parameters.recurrent_layers.iter_mut())
+            .enumerate()
+        {
+            ui.horizontal(|ui| {
+                egui::ComboBox::from_label(format!("Layer {} Activation", layer + 1))
+                    .selected_text(activation.name())
+                    .show_ui(ui, |ui| {
+                        for option in Activation::ALL {
+                            ui.selectable_value(activation, option, option.name());
+                        }
+                    });
+                ui.checkbox(recurrent, "Recurrent");
+            });
+        }
+        ui.label(format!(
+            "Parameters per network: {}",
+            gui_parameters.topology().parameter_count()
+        ));
+        // set weight initialization of new networks
+        let weight_init = &mut gui_parameters.weight_init;
+        egui::ComboBox::from_label("Weight Initialization")
+            .selected_text(weight_init.name())
+            .show_ui(ui, |ui| {
+                for option in WeightInit::ALL {
+                    if ui
+                        .selectable_label(weight_init.same_kind(&option), option.name())
+                        .clicked()
+                        && !weight_init.same_kind(&option)
+                    {
+                        *weight_init = option;
+                    }
+                }
+            });
+        match weight_init {
+            WeightInit::SmallNormal { sigma } => {
+                ui.horizontal(|ui| {
+                    ui.label("Initial Sigma");
+                    ui.add(egui::Slider::new(sigma, 0.001..=1.0).logarithmic(true));
+                });
+            }
+            WeightInit::Constant { value } => {
+                ui.horizontal(|ui| {
+                    ui.label("Initial Value");
+                    ui.add(egui::Slider::new(value, -1.0..=1.0));
+                });
+            }
+            WeightInit::Uniform | WeightInit::Xavier | WeightInit::He | WeightInit::Zeros => {}
+        }
+        // pre-train new networks on recorded demonstrations
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Dataset");
+            ui.text_edit_singleline(&mut gui_parameters.dataset_path);
+        });
+        let supervised = &mut gui_parameters.supervised;
+        egui::ComboBox::from_label("Loss")
+            .selected_text(supervised.loss.name())
+            .show_ui(ui, |ui| {
+                for option in Loss::ALL {
+                    ui.selectable_value(&mut supervised.loss, option, option.name());
+                }
+            });
+        ui.horizontal(|ui| {
+            ui.label("Learning Rate");
+            ui.add(
+                egui::Slider::new(&mut supervised.learning_rate, 0.00001..=1.0).logarithmic(true),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Batch Size");
+            ui.add(egui::Slider::new(&mut supervised.batch_size, 1..=512));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Epochs");
+            ui.add(egui::Slider::new(&mut supervised.epochs, 1..=1000));
+        });
+        if ui.button("Pre-train population").clicked() {
+            gui_parameters.pretrain_requested = true;
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Genome Directory");
+            ui.text_edit_singleline(&mut gui_parameters.genome_directory);
+        });
+        ui.horizontal(|ui| {
+            ui.radio_value(
+                &mut gui_parameters.genome_format,
+                GenomeFormat::Json,
+                "JSON",
+            );
+            ui.radio_value(
+                &mut gui_parameters.genome_format,
+                GenomeFormat::Binary,
+                "Binary",
+            );
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save hall of fame").clicked() {
+                match save_hall_of_fame(
+                    &hall_of_fame,
+                    &gui_parameters.genome_directory,
+                    gui_parameters.genome_format,
+                ) {
+                    Ok(()) => println!("Saved hall of fame to {}", gui_parameters.genome_directory),
+                    Err(err) => println!("Could not save hall of fame: {}", err),
+                }
+            }
+            if ui.button("Load hall of fame").clicked() {
+                match load_hall_of_fame(
+                    &mut hall_of_fame,
+                    &gui_parameters.genome_directory,
+                    gui_parameters.genome_format,
+                    &network_size,
+                ) {
+                    Ok(()) => {
+                        println!(
+                            "Loaded hall of fame from {}",
+                            gui_parameters.genome_directory
+                        );
+                        if let Some(best) = hall_of_fame.best() {
+                            gui_parameters.layer_activations = best.neural_network.activations();
+                            gui_parameters.recurrent_layers =
+                                best.neural_network.recurrent_layers();
+                            gui_parameters.weight_init = best.neural_network.weight_init();
+                        }
+                    }
+                    Err(err) => println!("Could not load hall of fame: {}", err),
+                }
+            }
+        });
         // set visible bird
         // let max_bird_count = gui_parameters.population_size;
         // ui.horizontal(|ui| {
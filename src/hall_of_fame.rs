@@ -0,0 +1,106 @@
+// The best genomes seen during a training run, best first
+use std::cmp::Ordering;
+
+use bevy::prelude::*;
+
+use crate::neural_network::NeuralNetwork;
+use crate::selection::Individual;
+
+pub const HALL_OF_FAME_SIZE: usize = 10;
+
+#[derive(Clone, Debug)]
+pub struct HallOfFameEntry {
+    pub neural_network: NeuralNetwork,
+    pub generation: usize, // Generation the bird lived in
+    pub fitness: f32,
+    pub score: f32,
+}
+
+impl HallOfFameEntry {
+    // Order by fitness, then by score
+    fn compare(&self, fitness: f32, score: f32) -> Ordering {
+        self.fitness
+            .total_cmp(&fitness)
+            .then(self.score.total_cmp(&score))
+    }
+}
+
+#[derive(Clone, Debug, Resource)]
+pub struct HallOfFame {
+    pub capacity: usize, // Number of genomes kept
+    entries: Vec<HallOfFameEntry>,
+}
+
+impl HallOfFame {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[HallOfFameEntry] {
+        &self.entries
+    }
+
+    pub fn best(&self) -> Option<&HallOfFameEntry> {
+        self.entries.first()
+    }
+
+    pub fn best_score(&self) -> f32 {
+        self.best().map_or(0.0, |entry| entry.score)
+    }
+
+    pub fn best_fitness(&self) -> f32 {
+        self.best().map_or(0.0, |entry| entry.fitness)
+    }
+
+    // Add a bird if it ranks among the best. The network is only cloned if it gets in.
+    // A network that is already in the hall, such as an elite, keeps only its best result.
+    pub fn consider(&mut self, individual: &Individual, generation: usize) {
+        let beats = |entry: &HallOfFameEntry| {
+            entry.compare(individual.fitness, individual.score) == Ordering::Less
+        };
+        if let Some(existing) = self
+            .entries
+            .iter()
+            .position(|entry| entry.neural_network == individual.neural_network)
+        {
+            if !beats(&self.entries[existing]) {
+                return;
+            }
+            self.entries.remove(existing);
+        }
+        // Ties go to the genome that was there first
+        let rank = self
+            .entries
+            .iter()
+            .position(beats)
+            .unwrap_or(self.entries.len());
+        if rank >= self.capacity {
+            return;
+        }
+        self.entries.insert(
+            rank,
+            HallOfFameEntry {
+                neural_network: individual.neural_network.clone(),
+                generation,
+                fitness: individual.fitness,
+                score: individual.score,
+            },
+        );
+        self.entries.truncate(self.capacity);
+    }
+
+    // Replace every entry, e.g. with genomes loaded from disk
+    pub fn replace(&mut self, mut entries: Vec<HallOfFameEntry>) {
+        entries.sort_by(|a, b| b.compare(a.fitness, a.score));
+        entries.truncate(self.capacity);
+        self.entries = entries;
+    }
+
+    // Keep only the best entries when the capacity is lowered
+    pub fn shrink_to_capacity(&mut self) {
+        self.entries.truncate(self.capacity);
+    }
+}
@@ -0,0 +1,164 @@
+use std::time::{Duration, Instant};
+
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::input::InputPlugin;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use crate::cli::CliArgs;
+use crate::components::{Bird, Brain, BrainKind, Simulation};
+use crate::genome_io::*;
+use crate::gui::*;
+use crate::hall_of_fame::HallOfFame;
+use crate::neat::NeatPopulation;
+use crate::selection::Individual;
+use crate::simulation::FIXED_TIMESTEP;
+use crate::TrainingPlugin;
+
+// Used when neither a generation count nor a target score is given
+pub const DEFAULT_HEADLESS_GENERATIONS: usize = 100;
+// A generation that has flown this many simulated seconds ends the run, as a bird that
+// has learned the course may never crash
+pub const MAX_GENERATION_SECONDS: f32 = 600.0;
+
+#[derive(Clone, Debug, Resource)]
+pub struct HeadlessConfig {
+    pub max_generations: Option<usize>,
+    pub target_score: Option<f32>,
+    pub genome_directory: String,
+    pub started_at: Instant,
+    pub reported_generation: usize,
+}
+
+// Train without a window. Every update advances time by exactly one physics step
+// and the next update starts immediately, so training runs as fast as the CPU allows.
+pub fn run_headless(args: &CliArgs, seed: u64) {
+    let max_generations = match (args.generations, args.target_score) {
+        (None, None) => Some(DEFAULT_HEADLESS_GENERATIONS),
+        (generations, _) => generations,
+    };
+    let mut gui_parameters = GuiParameters {
+        start_training: true,
+        population_size: args.population_size.unwrap_or(POPULATION_SIZE),
+        brain_kind: if args.neat {
+            BrainKind::Neat
+        } else {
+            BrainKind::Network
+        },
+        training_algorithm: args.algorithm.unwrap_or_default(),
+        pretrain_requested: args.pretrain.is_some(),
+        ..default()
+    };
+    if let Some(dataset_path) = &args.pretrain {
+        gui_parameters.dataset_path = dataset_path.clone();
+    }
+    let genome_directory = args
+        .genome_directory
+        .clone()
+        .unwrap_or_else(|| gui_parameters.genome_directory.clone());
+
+    App::new()
+        .add_plugins((
+            MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::ZERO)),
+            InputPlugin,
+            TrainingPlugin { seed },
+        ))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(
+            FIXED_TIMESTEP,
+        )))
+        .insert_resource(gui_parameters)
+        .insert_resource(HeadlessConfig {
+            max_generations,
+            target_score: args.target_score,
+            genome_directory,
+            started_at: Instant::now(),
+            reported_generation: 0,
+        })
+        .add_systems(Update, report_headless_progress)
+        .run();
+}
+
+pub fn report_headless_progress(
+    mut config: ResMut<HeadlessConfig>,
+    params: Res<GuiParameters>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+    mut neat: ResMut<NeatPopulation>,
+    simulation: Res<Simulation>,
+    bird_query: Query<&Bird>,
+    mut exit: EventWriter<AppExit>,
+) {
+    if params.current_generation != config.reported_generation {
+        config.reported_generation = params.current_generation;
+        println!(
+            "[headless] generation {:>5} | best score {:>6.2} | best fitness {:>8.2} | {:.1}s elapsed",
+            params.current_generation,
+            hall_of_fame.best_score().max(neat.best_score),
+            hall_of_fame.best_fitness().max(neat.best_fitness),
+            config.started_at.elapsed().as_secs_f32()
+        );
+    }
+
+    // Checked on every step, since the generation that reaches the target may never end
+    let generations_done = config
+        .max_generations
+        .is_some_and(|max| params.current_generation >= max);
+    let target_reached = config.target_score.is_some_and(|target| {
+        hall_of_fame
+            .best_score()
+            .max(neat.best_score)
+            .max(simulation.best_living_score())
+            >= target
+    });
+    let flight_too_long = simulation.time() >= MAX_GENERATION_SECONDS;
+    if !generations_done && !target_reached && !flight_too_long {
+        return;
+    }
+    if flight_too_long {
+        println!(
+            "[headless] a bird has flown for {:.0}s without crashing",
+            simulation.time()
+        );
+    }
+    // Birds still flying have not been judged yet
+    for bird in bird_query.iter() {
+        let Some(state) = simulation.birds().get(bird.index) else {
+            continue;
+        };
+        if state.dead {
+            continue;
+        }
+        match &bird.brain {
+            Brain::Network(neural_network) => hall_of_fame.consider(
+                &Individual {
+                    neural_network: neural_network.clone(),
+                    fitness: state.fitness,
+                    score: state.score,
+                    flaps: state.flaps,
+                    behaviour: Vec::new(),
+                },
+                params.current_generation,
+            ),
+            Brain::Neat(_) => neat.record(bird.index, state.fitness, state.score),
+            Brain::Human => {}
+        }
+    }
+    println!(
+        "[headless] finished at generation {} | best score {:.2} | best fitness {:.2}",
+        params.current_generation,
+        hall_of_fame.best_score().max(neat.best_score),
+        hall_of_fame.best_fitness().max(neat.best_fitness)
+    );
+    if params.brain_kind == BrainKind::Neat {
+        // The genome files only hold fixed-architecture networks
+        println!("[headless] NEAT genomes cannot be saved, nothing was written");
+    } else {
+        match save_hall_of_fame(&hall_of_fame, &config.genome_directory, GenomeFormat::Json) {
+            Ok(()) => println!(
+                "[headless] saved hall of fame to {}",
+                config.genome_directory
+            ),
+            Err(err) => println!("[headless] could not save hall of fame: {}", err),
+        }
+    }
+    exit.send(AppExit);
+}
@@ -0,0 +1,84 @@
+// Schemes for drawing the weights and biases of new networks
+use rand::prelude::*;
+use rand_distr::Normal;
+use serde::{Deserialize, Serialize};
+
+// Networks saved before the scheme was recorded were all drawn uniformly
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum WeightInit {
+    // Weights and biases uniformly in -1..1
+    #[default]
+    Uniform,
+    // Weights uniformly in ±sqrt(6 / (fan_in + fan_out)) and zero biases, suits Sigmoid and Tanh
+    Xavier,
+    // Weights normal with standard deviation sqrt(2 / fan_in) and zero biases, suits ReLU
+    He,
+    // Weights and biases normal with standard deviation sigma
+    SmallNormal {
+        sigma: f32,
+    },
+    Zeros,
+    // Every weight and bias set to value
+    Constant {
+        value: f32,
+    },
+}
+
+impl WeightInit {
+    // One of each kind with its default setting, for the GUI
+    pub const ALL: [WeightInit; 6] = [
+        WeightInit::Uniform,
+        WeightInit::Xavier,
+        WeightInit::He,
+        WeightInit::SmallNormal { sigma: 0.1 },
+        WeightInit::Zeros,
+        WeightInit::Constant { value: 0.1 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            WeightInit::Uniform => "Uniform",
+            WeightInit::Xavier => "Xavier/Glorot",
+            WeightInit::He => "He",
+            WeightInit::SmallNormal { .. } => "Small Normal",
+            WeightInit::Zeros => "Zeros",
+            WeightInit::Constant { .. } => "Constant",
+        }
+    }
+
+    // True if both are the same kind of scheme, whatever their settings
+    pub fn same_kind(&self, other: &WeightInit) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    // A weight of a neuron with fan_in inputs feeding a layer of fan_out neurons
+    pub fn weight(&self, fan_in: usize, fan_out: usize, rng: &mut impl Rng) -> f32 {
+        match *self {
+            WeightInit::Uniform => rng.gen::<f32>() * 2.0 - 1.0,
+            WeightInit::Xavier => {
+                let limit = (6.0 / (fan_in + fan_out).max(1) as f32).sqrt();
+                rng.gen_range(-limit..=limit)
+            }
+            WeightInit::He => normal(rng, (2.0 / fan_in.max(1) as f32).sqrt()),
+            WeightInit::SmallNormal { sigma } => normal(rng, sigma),
+            WeightInit::Zeros => 0.0,
+            WeightInit::Constant { value } => value,
+        }
+    }
+
+    pub fn bias(&self, rng: &mut impl Rng) -> f32 {
+        match *self {
+            WeightInit::Uniform => rng.gen::<f32>() * 2.0 - 1.0,
+            WeightInit::Xavier | WeightInit::He | WeightInit::Zeros => 0.0,
+            WeightInit::SmallNormal { sigma } => normal(rng, sigma),
+            WeightInit::Constant { value } => value,
+        }
+    }
+}
+
+fn normal(rng: &mut impl Rng, sigma: f32) -> f32 {
+    match Normal::new(0.0, sigma) {
+        Ok(normal) => normal.sample(rng),
+        Err(_) => 0.0,
+    }
+}
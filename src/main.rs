@@ -1,51 +1,123 @@
+mod batch;
+mod cli;
+mod cma_es;
 mod components;
+mod crossover;
+mod dataset;
+mod differential_evolution;
+mod genome_io;
 mod gui;
+mod hall_of_fame;
+mod headless;
+mod initialization;
+mod mutation;
+mod neat;
 mod neural_network;
+mod novelty;
+mod openai_es;
+mod optimizer;
+mod pareto;
+mod particle_swarm;
+mod random;
+mod rendering;
+mod selection;
+mod simulation;
+mod supervised;
 mod systems;
 
+use crate::cli::*;
 use crate::components::*;
 use crate::gui::*;
-use crate::neural_network::*;
+use crate::hall_of_fame::*;
+use crate::headless::*;
+use crate::neat::*;
+use crate::novelty::*;
+use crate::optimizer::*;
+use crate::pareto::*;
+use crate::random::*;
+use crate::rendering::*;
+use crate::simulation::*;
 use crate::systems::*;
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use bevy::prelude::*;
 use bevy_egui::EguiPlugin;
 use rand::prelude::*;
 
+#[allow(non_upper_case_globals)]
 pub const network_size: [usize; 5] = [4, 3, 3, 2, 1];
 
 fn main() {
+    let args = match CliArgs::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+    let seed = args.seed.unwrap_or_else(random);
+    println!("Seed: {}", seed);
+    if args.headless {
+        run_headless(&args, seed);
+        return;
+    }
     App::new()
-        .add_plugins((DefaultPlugins, EguiPlugin))
-        .insert_resource(GuiParameters::default())
-        .insert_resource(BestBirds {
-            best_neural_network: NeuralNetwork::new(&network_size),
-            second_best_neural_network: NeuralNetwork::new(&network_size),
-            best_score: 0.0,
-            second_best_score: 0.0,
-            best_fitness: 0.0,
-            second_best_fitness: 0.0,
-        })
-        .add_systems(Startup, (set_window_size, setup, spawn_bird))
+        .add_plugins((DefaultPlugins, EguiPlugin, TrainingPlugin { seed }))
+        .add_systems(Startup, (set_window_size, setup, setup_sprite_assets))
         .add_systems(
             Update,
             (
                 update_gui,
-                gravity_system,
-                jump_system,
-                move_bird,
-                spawn_pipe,
-                despawn_pipe,
-                update_my_time,
-                move_pipes,
-                update_environment_state,
-                update_fitness,
-                check_collision,
-                //generate_next_generation,
-                generate_next_generation_thirds,
+                apply_activation_changes,
+                apply_recurrence_changes,
+                add_bird_sprites,
+                sync_bird_sprites,
+                sync_pipe_sprites,
             ),
         )
         .run();
 }
+
+// Resources and systems shared by the windowed and the headless app
+pub struct TrainingPlugin {
+    pub seed: u64,
+}
+
+impl Plugin for TrainingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(HallOfFame::new(HALL_OF_FAME_SIZE))
+            .insert_resource(GuiParameters::default())
+            .insert_resource(TrainingRng::new(self.seed))
+            .insert_resource(BirdActions::default())
+            .insert_resource(PopulationBrains::default())
+            .insert_resource(EvaluatedPopulation::default())
+            .insert_resource(NeatPopulation::default())
+            .insert_resource(ParameterSearch::default())
+            .insert_resource(NoveltyArchive::default())
+            .insert_resource(ParetoFront::default())
+            .insert_resource(HumanPlay::default())
+            .insert_resource(FixedTime::new_from_secs(FIXED_TIMESTEP))
+            .add_systems(Startup, spawn_bird)
+            // The simulation runs in a fixed order on a fixed timestep, so a given
+            // course and set of networks always plays out the same way.
+            .add_systems(
+                FixedUpdate,
+                (
+                    rebuild_population_brains,
+                    jump_system,
+                    step_simulation,
+                    check_collision,
+                    generate_next_generation_human,
+                    pretrain_population,
+                    restart_training,
+                    //generate_next_generation,
+                    generate_next_generation_thirds,
+                    generate_next_generation_search,
+                    generate_next_generation_neat,
+                )
+                    .chain(),
+            )
+            .add_systems(Update, read_human_input);
+    }
+}
 /* INPUTS TO NEURAL NETWORK*/
 // Horizontal distance next pipe: f32 x
 // Vertical gap position center of next pipe: f32 y
@@ -60,10 +132,10 @@ fn main() {
 // Number of outputs: 1
 // Number of hidden layers: 1
 // Number of neurons in hidden layer: 3
-// Activation function: Sigmoid
+// Activation function: Sigmoid by default, selectable per layer
 
 /* SCORING */
-// Score += 0.5 per top and bottom half: f32 1.0 per pipe
+// Score += 1.0 per pipe pair, counted separately for every bird
 
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle::default());
@@ -71,152 +143,17 @@ fn setup(mut commands: Commands) {
 
 fn spawn_bird(
     mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
     gui_parameters: ResMut<GuiParameters>,
+    mut rng: ResMut<TrainingRng>,
+    mut neat: ResMut<NeatPopulation>,
 ) {
-    for _ in 0..gui_parameters.population_size {
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    translation: Vec3::new(SPAWN_X_POINT, 0.0, 0.0),
-                    scale: Vec3::new(BIRD_SIZE, BIRD_SIZE, 0.0),
-                    ..Default::default()
-                },
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                ..default()
-            },
-            Bird {
-                velocity: 0.0,
-                score: 0.0,
-                fitness: 0.0,
-                dead: false,
-                neural_network: NeuralNetwork::new(&network_size),
-            },
-            Environment::default(),
-        ));
-    }
-}
-
-pub fn update_my_time(mut params: ResMut<GuiParameters>, time: Res<Time>) {
-    if !params.start_training {
-        return;
-    }
-    params.passed_time_since_start += time.delta_seconds();
-    params.passed_time_since_last_pipe += time.delta_seconds();
-}
-
-pub fn update_fitness(mut query: Query<&mut Bird>, time: Res<Time>, params: ResMut<GuiParameters>) {
-    if !params.start_training {
-        return;
-    }
-    for mut bird in query.iter_mut() {
-        bird.fitness += time.delta_seconds();
-    }
-}
-
-fn spawn_pipe(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut params: ResMut<GuiParameters>,
-) {
-    if params.passed_time_since_last_pipe < 3.0 || !params.start_training {
-        return;
-    }
-    params.passed_time_since_last_pipe = 0.0;
-    let mut rng = rand::thread_rng();
-    let gc = rng.gen_range((-WINDOW_HEIGHT / 2.0 + GAP_WIDTH)..(WINDOW_HEIGHT / 2.0 - GAP_WIDTH));
-    let y_pos1 = gc - WINDOW_HEIGHT / 2.0 - GAP_WIDTH / 2.0;
-    let y_pos2 = gc + WINDOW_HEIGHT / 2.0 + GAP_WIDTH / 2.0;
-    commands.spawn((
-        Pipe {
-            velocity: -PIPE_VELOCITY,
-            gap: GAP_WIDTH,
-            gap_center: gc,
-            bird_passed: false,
-        },
-        MaterialMesh2dBundle {
-            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-            transform: Transform {
-                translation: Vec3::new(WINDOW_WIDTH / 2.0 - PIPE_WIDTH, y_pos1, 0.0),
-                scale: Vec3::new(PIPE_WIDTH, WINDOW_HEIGHT, 0.0),
-                ..Default::default()
-            },
-            material: materials.add(ColorMaterial::from(Color::GREEN)),
-            ..default()
-        },
-    ));
-    commands.spawn((
-        Pipe {
-            velocity: -PIPE_VELOCITY,
-            gap: GAP_WIDTH,
-            gap_center: gc,
-            bird_passed: false,
-        },
-        MaterialMesh2dBundle {
-            mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-            transform: Transform {
-                translation: Vec3::new(WINDOW_WIDTH / 2.0 - PIPE_WIDTH, y_pos2, 0.0),
-                scale: Vec3::new(PIPE_WIDTH, WINDOW_HEIGHT, 0.0),
-                ..Default::default()
-            },
-            material: materials.add(ColorMaterial::from(Color::GREEN)),
-            ..default()
-        },
-    ));
-}
-
-pub fn despawn_pipe(
-    mut commands: Commands,
-    mut query: Query<(Entity, &Pipe, &Transform)>,
-    mut _params: ResMut<GuiParameters>,
-) {
-    for (entity, _pipe, transform) in query.iter_mut() {
-        if transform.translation.x < -WINDOW_WIDTH / 2.0 - PIPE_WIDTH / 2.0 {
-            commands.entity(entity).despawn();
-        }
-    }
-}
-
-pub fn move_pipes(mut query: Query<(&mut Transform, &Pipe)>, time: Res<Time>) {
-    for (mut transform, pipe) in query.iter_mut() {
-        transform.translation.x += pipe.velocity * time.delta_seconds();
-    }
+    let brains = first_generation_brains(&gui_parameters, &mut rng, &mut neat);
+    let mut simulation = FlappyWorld::new(PhysicsConfig::default(), 0, 0);
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    commands.insert_resource(Simulation(simulation));
 }
 
 fn set_window_size(mut window: Query<&mut Window>) {
     let mut w = window.single_mut();
     w.resolution.set(WINDOW_WIDTH, WINDOW_HEIGHT);
 }
-
-pub fn update_environment_state(
-    mut bird_query: Query<(&Transform, &mut Bird, &mut Environment)>,
-    mut pipe_query: Query<(&Transform, &mut Pipe)>,
-
-    mut params: ResMut<GuiParameters>,
-) {
-    if !params.start_training {
-        return;
-    }
-    for (bird_transform, mut bird, mut environment) in bird_query.iter_mut() {
-        let mut nearest_pipe_dist: f32 = f32::MAX;
-        let mut nearest_pipe_vertical_center: f32 = f32::MAX;
-        for (pipe_transform, mut pipe) in pipe_query.iter_mut() {
-            if pipe_transform.translation.x < bird_transform.translation.x && !pipe.bird_passed {
-                pipe.bird_passed = true;
-                bird.score += 0.5;
-                params.current_score += 0.5;
-                continue; // Pipe is behind bird
-            }
-            let temporary_dist: f32 = pipe_transform.translation.x - bird_transform.translation.x;
-            if temporary_dist < nearest_pipe_dist {
-                nearest_pipe_dist = temporary_dist;
-                nearest_pipe_vertical_center = pipe.gap_center;
-            }
-        }
-        environment.horizontal_distance = nearest_pipe_dist;
-        environment.vertical_gap_position = nearest_pipe_vertical_center;
-    }
-}
@@ -0,0 +1,193 @@
+// Mutation of child networks. Every weight and bias is a gene that mutates on its own.
+use rand::prelude::*;
+use rand_distr::Normal;
+
+use crate::neural_network::{NetworkError, NeuralNetwork};
+
+// Changes the value of a single gene
+pub trait MutationOperator {
+    fn mutate_gene(&self, gene: f32, rng: &mut dyn RngCore) -> f32;
+}
+
+// Add a uniformly distributed offset in -rate..rate
+pub struct UniformPerturbation {
+    pub rate: f32,
+}
+
+impl MutationOperator for UniformPerturbation {
+    fn mutate_gene(&self, gene: f32, rng: &mut dyn RngCore) -> f32 {
+        let rate = self.rate.abs();
+        if rate == 0.0 {
+            return gene;
+        }
+        gene + rng.gen_range(-rate..rate)
+    }
+}
+
+// Add a normally distributed offset with standard deviation sigma
+pub struct GaussianPerturbation {
+    pub sigma: f32,
+}
+
+impl MutationOperator for GaussianPerturbation {
+    fn mutate_gene(&self, gene: f32, rng: &mut dyn RngCore) -> f32 {
+        match Normal::new(0.0, self.sigma) {
+            Ok(normal) => gene + normal.sample(rng),
+            Err(_) => gene,
+        }
+    }
+}
+
+// Replace the gene with a new value in -range..range, like a freshly initialized weight
+pub struct RandomReset {
+    pub range: f32,
+}
+
+impl MutationOperator for RandomReset {
+    fn mutate_gene(&self, _gene: f32, rng: &mut dyn RngCore) -> f32 {
+        let range = self.range.abs();
+        if range == 0.0 {
+            return 0.0;
+        }
+        rng.gen_range(-range..range)
+    }
+}
+
+// Negate the gene
+pub struct SignFlip;
+
+impl MutationOperator for SignFlip {
+    fn mutate_gene(&self, gene: f32, _rng: &mut dyn RngCore) -> f32 {
+        -gene
+    }
+}
+
+// Operator choice for the GUI. The settings of each operator live in GuiParameters.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MutationKind {
+    #[default]
+    Uniform,
+    Gaussian,
+    Reset,
+    SignFlip,
+}
+
+impl MutationKind {
+    pub const ALL: [MutationKind; 4] = [
+        MutationKind::Uniform,
+        MutationKind::Gaussian,
+        MutationKind::Reset,
+        MutationKind::SignFlip,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MutationKind::Uniform => "Uniform",
+            MutationKind::Gaussian => "Gaussian",
+            MutationKind::Reset => "Random Reset",
+            MutationKind::SignFlip => "Sign Flip",
+        }
+    }
+}
+
+// Mutate each gene with the given probability. A network whose weights do not match
+// its layer sizes is left unchanged.
+pub fn mutate(
+    nn: &mut NeuralNetwork,
+    mutation_probability: f32,
+    operator: &dyn MutationOperator,
+    rng: &mut dyn RngCore,
+) -> Result<(), NetworkError> {
+    nn.check_consistent()?;
+    for gene in nn.genes_mut() {
+        if rng.gen::<f32>() < mutation_probability {
+            *gene = operator.mutate_gene(*gene, rng);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initialization::WeightInit;
+    use crate::neural_network::{Activation, Topology};
+
+    fn network(rng: &mut StdRng) -> NeuralNetwork {
+        let topology = Topology::new(
+            &[4, 8, 8, 1],
+            &[Activation::Tanh, Activation::Tanh, Activation::Sigmoid],
+            &[false, true, false],
+        );
+        NeuralNetwork::from_topology(&topology, WeightInit::Xavier, rng)
+    }
+
+    #[test]
+    fn perturbations_stay_in_range() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let uniform = UniformPerturbation { rate: 0.2 };
+        let reset = RandomReset { range: 0.5 };
+        for _ in 0..1_000 {
+            let offset = uniform.mutate_gene(1.0, &mut rng) - 1.0;
+            assert!((-0.2..0.2).contains(&offset));
+            assert!((-0.5..0.5).contains(&reset.mutate_gene(7.0, &mut rng)));
+        }
+        assert_eq!(SignFlip.mutate_gene(0.75, &mut rng), -0.75);
+    }
+
+    #[test]
+    fn gaussian_offsets_have_the_given_deviation() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let gaussian = GaussianPerturbation { sigma: 0.3 };
+        let offsets: Vec<f32> = (0..10_000)
+            .map(|_| gaussian.mutate_gene(2.0, &mut rng) - 2.0)
+            .collect();
+        let mean = offsets.iter().sum::<f32>() / offsets.len() as f32;
+        let deviation = (offsets.iter().map(|o| (o - mean) * (o - mean)).sum::<f32>()
+            / offsets.len() as f32)
+            .sqrt();
+        assert!(mean.abs() < 0.02, "{}", mean);
+        assert!((deviation - 0.3).abs() < 0.02, "{}", deviation);
+    }
+
+    #[test]
+    fn zero_or_invalid_settings_do_not_break_genes() {
+        let mut rng = StdRng::seed_from_u64(5);
+        assert_eq!(
+            UniformPerturbation { rate: 0.0 }.mutate_gene(1.5, &mut rng),
+            1.5
+        );
+        assert_eq!(
+            GaussianPerturbation { sigma: f32::NAN }.mutate_gene(1.5, &mut rng),
+            1.5
+        );
+        assert_eq!(RandomReset { range: 0.0 }.mutate_gene(1.5, &mut rng), 0.0);
+    }
+
+    #[test]
+    fn each_gene_mutates_with_the_given_probability() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let parent = network(&mut rng);
+        let gene_count = parent.genes().count();
+
+        let mut child = parent.clone();
+        mutate(&mut child, 0.0, &SignFlip, &mut rng).unwrap();
+        assert_eq!(child, parent);
+
+        mutate(&mut child, 1.0, &SignFlip, &mut rng).unwrap();
+        assert!(child.genes().zip(parent.genes()).all(|(c, p)| *c == -*p));
+
+        let mut flipped = 0;
+        for _ in 0..20 {
+            let mut child = parent.clone();
+            mutate(&mut child, 0.3, &SignFlip, &mut rng).unwrap();
+            flipped += child
+                .genes()
+                .zip(parent.genes())
+                .filter(|(c, p)| *c != *p)
+                .count();
+        }
+        let share = flipped as f32 / (20 * gene_count) as f32;
+        assert!((share - 0.3).abs() < 0.03, "{}", share);
+    }
+}
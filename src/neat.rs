@@ -0,0 +1,809 @@
+// NEAT-style genomes whose topology evolves along with the weights. Networks start with
+// the inputs connected straight to the outputs and only grow hidden nodes and
+// connections through mutation. Genomes are grouped into species by compatibility
+// distance so new structure gets a few generations to tune its weights.
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::prelude::*;
+
+use crate::mutation::MutationOperator;
+use crate::neural_network::Activation;
+use crate::random::TrainingRng;
+
+// Weights of the distance between two genomes
+const EXCESS_COEFFICIENT: f32 = 1.0;
+const DISJOINT_COEFFICIENT: f32 = 1.0;
+const WEIGHT_COEFFICIENT: f32 = 0.4;
+const CROSSOVER_PROBABILITY: f32 = 0.75;
+const DISABLED_GENE_PROBABILITY: f64 = 0.75; // Gene disabled in either parent stays disabled
+const CHAMPION_SPECIES_SIZE: usize = 5; // Species at least this big keep their best genome
+const ADD_CONNECTION_ATTEMPTS: usize = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NodeKind {
+    Input,
+    Bias, // Always outputs 1
+    Hidden,
+    Output,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeGene {
+    pub id: u32,
+    pub kind: NodeKind,
+    pub activation: Activation,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConnectionGene {
+    pub innovation: u32, // Same structural change in any genome gets the same number
+    pub from: u32,
+    pub to: u32,
+    pub weight: f32,
+    pub enabled: bool,
+}
+
+// Hands out innovation numbers and node ids, so that genomes which make the same
+// structural change end up with matching genes
+#[derive(Clone, Debug, Default)]
+pub struct InnovationTracker {
+    connections: HashMap<(u32, u32), u32>,
+    splits: HashMap<u32, u32>, // Innovation of a split connection to the id of its new node
+    next_innovation: u32,
+    next_node: u32,
+}
+
+impl InnovationTracker {
+    fn connection(&mut self, from: u32, to: u32) -> u32 {
+        let next = &mut self.next_innovation;
+        *self.connections.entry((from, to)).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        })
+    }
+
+    fn split_node(&mut self, innovation: u32) -> u32 {
+        let next = &mut self.next_node;
+        *self.splits.entry(innovation).or_insert_with(|| {
+            *next += 1;
+            *next - 1
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct NeatGenome {
+    nodes: Vec<NodeGene>,
+    connections: Vec<ConnectionGene>, // Sorted by innovation
+}
+
+impl NeatGenome {
+    // Inputs and a bias node connected straight to every output
+    pub fn minimal(
+        input_count: usize,
+        output_count: usize,
+        tracker: &mut InnovationTracker,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut nodes = Vec::with_capacity(input_count + 1 + output_count);
+        for id in 0..input_count as u32 {
+            nodes.push(NodeGene {
+                id,
+                kind: NodeKind::Input,
+                activation: Activation::Linear,
+            });
+        }
+        nodes.push(NodeGene {
+            id: input_count as u32,
+            kind: NodeKind::Bias,
+            activation: Activation::Linear,
+        });
+        for output in 0..output_count as u32 {
+            nodes.push(NodeGene {
+                id: input_count as u32 + 1 + output,
+                kind: NodeKind::Output,
+                activation: Activation::Sigmoid,
+            });
+        }
+        tracker.next_node = tracker.next_node.max(nodes.len() as u32);
+
+        let mut connections = Vec::new();
+        for from in nodes.iter().filter(|node| node.kind != NodeKind::Output) {
+            for to in nodes.iter().filter(|node| node.kind == NodeKind::Output) {
+                connections.push(ConnectionGene {
+                    innovation: tracker.connection(from.id, to.id),
+                    from: from.id,
+                    to: to.id,
+                    weight: rng.gen_range(-1.0..1.0),
+                    enabled: true,
+                });
+            }
+        }
+        connections.sort_by_key(|connection| connection.innovation);
+        Self { nodes, connections }
+    }
+
+    pub fn hidden_node_count(&self) -> usize {
+        self.nodes
+            .iter()
+            .filter(|node| node.kind == NodeKind::Hidden)
+            .count()
+    }
+
+    pub fn enabled_connection_count(&self) -> usize {
+        self.connections
+            .iter()
+            .filter(|connection| connection.enabled)
+            .count()
+    }
+
+    fn node(&self, id: u32) -> Option<&NodeGene> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    // Mutate each connection weight with the given probability
+    pub fn mutate_weights(
+        &mut self,
+        mutation_probability: f32,
+        operator: &dyn MutationOperator,
+        rng: &mut dyn RngCore,
+    ) {
+        for connection in &mut self.connections {
+            if rng.gen::<f32>() < mutation_probability {
+                connection.weight = operator.mutate_gene(connection.weight, rng);
+            }
+        }
+    }
+
+    // Connect two nodes that are not connected yet, never creating a cycle
+    pub fn mutate_add_connection(&mut self, tracker: &mut InnovationTracker, rng: &mut impl Rng) {
+        for _ in 0..ADD_CONNECTION_ATTEMPTS {
+            let from = &self.nodes[rng.gen_range(0..self.nodes.len())];
+            let to = &self.nodes[rng.gen_range(0..self.nodes.len())];
+            if from.kind == NodeKind::Output
+                || matches!(to.kind, NodeKind::Input | NodeKind::Bias)
+                || from.id == to.id
+            {
+                continue;
+            }
+            let (from, to) = (from.id, to.id);
+            if self
+                .connections
+                .iter()
+                .any(|connection| connection.from == from && connection.to == to)
+                || self.reaches(to, from)
+            {
+                continue;
+            }
+            let innovation = tracker.connection(from, to);
+            self.insert_connection(ConnectionGene {
+                innovation,
+                from,
+                to,
+                weight: rng.gen_range(-1.0..1.0),
+                enabled: true,
+            });
+            return;
+        }
+    }
+
+    // Split an enabled connection in two with a new hidden node in between. The incoming
+    // connection has weight 1 and the outgoing one the old weight, so the network
+    // behaves almost the same as before.
+    pub fn mutate_add_node(&mut self, tracker: &mut InnovationTracker, rng: &mut impl Rng) {
+        let enabled: Vec<usize> = (0..self.connections.len())
+            .filter(|index| self.connections[*index].enabled)
+            .collect();
+        let Some(&split) = enabled.choose(rng) else {
+            return;
+        };
+        let old = self.connections[split].clone();
+        let node = tracker.split_node(old.innovation);
+        if self.node(node).is_some() {
+            // This genome already split the connection before it was disabled
+            return;
+        }
+        self.connections[split].enabled = false;
+        self.nodes.push(NodeGene {
+            id: node,
+            kind: NodeKind::Hidden,
+            activation: Activation::Sigmoid,
+        });
+        let incoming = tracker.connection(old.from, node);
+        let outgoing = tracker.connection(node, old.to);
+        self.insert_connection(ConnectionGene {
+            innovation: incoming,
+            from: old.from,
+            to: node,
+            weight: 1.0,
+            enabled: true,
+        });
+        self.insert_connection(ConnectionGene {
+            innovation: outgoing,
+            from: node,
+            to: old.to,
+            weight: old.weight,
+            enabled: true,
+        });
+    }
+
+    fn insert_connection(&mut self, connection: ConnectionGene) {
+        let position = self
+            .connections
+            .partition_point(|existing| existing.innovation < connection.innovation);
+        self.connections.insert(position, connection);
+    }
+
+    // True if there is a path of connections from one node to another
+    fn reaches(&self, from: u32, to: u32) -> bool {
+        let mut stack = vec![from];
+        let mut visited = vec![from];
+        while let Some(node) = stack.pop() {
+            if node == to {
+                return true;
+            }
+            for connection in self.connections.iter().filter(|c| c.from == node) {
+                if !visited.contains(&connection.to) {
+                    visited.push(connection.to);
+                    stack.push(connection.to);
+                }
+            }
+        }
+        false
+    }
+
+    // Matching genes are inherited from either parent at random, disjoint and excess
+    // genes only from the fitter parent. The child has the structure of the fitter parent,
+    // so it stays free of cycles.
+    pub fn crossover(fitter: &NeatGenome, other: &NeatGenome, rng: &mut impl Rng) -> NeatGenome {
+        let connections = fitter
+            .connections
+            .iter()
+            .map(|gene| {
+                match other
+                    .connections
+                    .binary_search_by_key(&gene.innovation, |c| c.innovation)
+                {
+                    Ok(index) => {
+                        let matching = &other.connections[index];
+                        let mut child_gene = if rng.gen_bool(0.5) {
+                            gene.clone()
+                        } else {
+                            matching.clone()
+                        };
+                        child_gene.enabled = !((!gene.enabled || !matching.enabled)
+                            && rng.gen_bool(DISABLED_GENE_PROBABILITY));
+                        child_gene
+                    }
+                    Err(_) => gene.clone(),
+                }
+            })
+            .collect();
+        NeatGenome {
+            nodes: fitter.nodes.clone(),
+            connections,
+        }
+    }
+
+    // How far apart two genomes are in structure and weights
+    pub fn compatibility(&self, other: &NeatGenome) -> f32 {
+        let last_innovation = |genome: &NeatGenome| {
+            genome
+                .connections
+                .last()
+                .map_or(0, |connection| connection.innovation)
+        };
+        let excess_limit = last_innovation(self).min(last_innovation(other));
+        let (mut excess, mut disjoint, mut matching, mut weight_difference) = (0, 0, 0, 0.0);
+        let (mut a, mut b) = (
+            self.connections.iter().peekable(),
+            other.connections.iter().peekable(),
+        );
+        loop {
+            let unmatched = match (a.peek(), b.peek()) {
+                (Some(gene_a), Some(gene_b)) if gene_a.innovation == gene_b.innovation => {
+                    matching += 1;
+                    weight_difference += (gene_a.weight - gene_b.weight).abs();
+                    a.next();
+                    b.next();
+                    continue;
+                }
+                (Some(gene_a), Some(gene_b)) if gene_a.innovation < gene_b.innovation => {
+                    a.next().map(|gene| gene.innovation)
+                }
+                (Some(_), Some(_)) => b.next().map(|gene| gene.innovation),
+                (Some(_), None) => a.next().map(|gene| gene.innovation),
+                (None, Some(_)) => b.next().map(|gene| gene.innovation),
+                (None, None) => break,
+            };
+            match unmatched {
+                Some(innovation) if innovation > excess_limit => excess += 1,
+                _ => disjoint += 1,
+            }
+        }
+        let genes = self.connections.len().max(other.connections.len());
+        // Small genomes are not normalized, as in the original NEAT
+        let normalizer = if genes < 20 { 1.0 } else { genes as f32 };
+        let mean_weight_difference = if matching > 0 {
+            weight_difference / matching as f32
+        } else {
+            0.0
+        };
+        EXCESS_COEFFICIENT * excess as f32 / normalizer
+            + DISJOINT_COEFFICIENT * disjoint as f32 / normalizer
+            + WEIGHT_COEFFICIENT * mean_weight_difference
+    }
+
+    // Network that evaluates the genome without allocating
+    pub fn compile(&self) -> NeatNetwork {
+        let index_of: HashMap<u32, usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, index))
+            .collect();
+        let enabled: Vec<&ConnectionGene> = self.connections.iter().filter(|c| c.enabled).collect();
+
+        // Kahn's algorithm, starting from every node without incoming connections
+        let mut incoming_count = vec![0; self.nodes.len()];
+        for connection in &enabled {
+            incoming_count[index_of[&connection.to]] += 1;
+        }
+        let mut ready: Vec<usize> = (0..self.nodes.len())
+            .filter(|index| incoming_count[*index] == 0)
+            .collect();
+        let mut order = Vec::with_capacity(self.nodes.len());
+        while let Some(index) = ready.pop() {
+            order.push(index);
+            for connection in enabled.iter().filter(|c| c.from == self.nodes[index].id) {
+                let to = index_of[&connection.to];
+                incoming_count[to] -= 1;
+                if incoming_count[to] == 0 {
+                    ready.push(to);
+                }
+            }
+        }
+
+        let mut steps = Vec::new();
+        let mut links = Vec::new();
+        for index in order {
+            let node = &self.nodes[index];
+            if matches!(node.kind, NodeKind::Input | NodeKind::Bias) {
+                continue;
+            }
+            let start = links.len();
+            links.extend(
+                enabled
+                    .iter()
+                    .filter(|c| c.to == node.id)
+                    .map(|c| (index_of[&c.from], c.weight)),
+            );
+            steps.push(NeatStep {
+                node: index,
+                activation: node.activation,
+                links: start..links.len(),
+            });
+        }
+        let of_kind = |kind: NodeKind| -> Vec<usize> {
+            (0..self.nodes.len())
+                .filter(|index| self.nodes[*index].kind == kind)
+                .collect()
+        };
+        let outputs = of_kind(NodeKind::Output);
+        NeatNetwork {
+            inputs: of_kind(NodeKind::Input),
+            biases: of_kind(NodeKind::Bias),
+            output_values: vec![0.0; outputs.len()],
+            outputs,
+            steps,
+            links,
+            values: vec![0.0; self.nodes.len()],
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct NeatStep {
+    node: usize,
+    activation: Activation,
+    links: std::ops::Range<usize>, // Incoming (node, weight) pairs in NeatNetwork::links
+}
+
+// A genome laid out for evaluation, with nodes in topological order
+#[derive(Clone, Debug)]
+pub struct NeatNetwork {
+    inputs: Vec<usize>,
+    biases: Vec<usize>,
+    outputs: Vec<usize>,
+    steps: Vec<NeatStep>,
+    links: Vec<(usize, f32)>,
+    values: Vec<f32>,
+    output_values: Vec<f32>,
+}
+
+impl NeatNetwork {
+    pub fn forward(&mut self, input: &[f32]) -> &[f32] {
+        for (node, value) in self.inputs.iter().zip(input) {
+            self.values[*node] = *value;
+        }
+        for node in &self.biases {
+            self.values[*node] = 1.0;
+        }
+        for step in &self.steps {
+            let sum = self.links[step.links.clone()]
+                .iter()
+                .map(|(from, weight)| self.values[*from] * weight)
+                .sum::<f32>();
+            self.values[step.node] = step.activation.apply(sum);
+        }
+        for (value, node) in self.output_values.iter_mut().zip(&self.outputs) {
+            *value = self.values[*node];
+        }
+        &self.output_values
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Species {
+    pub representative: NeatGenome,
+    pub members: Vec<usize>, // Indices into NeatPopulation::genomes
+}
+
+// Settings of one round of NEAT reproduction
+pub struct NeatSettings<'a> {
+    pub population_size: usize,
+    pub compatibility_threshold: f32,
+    pub add_node_probability: f32,
+    pub add_connection_probability: f32,
+    pub mutation_probability: f32, // Per connection weight
+    pub mutation_operator: &'a dyn MutationOperator,
+}
+
+// The NEAT genomes of the current generation, indexed like the birds
+#[derive(Clone, Debug, Default, Resource)]
+pub struct NeatPopulation {
+    pub genomes: Vec<NeatGenome>,
+    pub fitness: Vec<f32>,
+    pub species: Vec<Species>,
+    pub best_fitness: f32, // Best results of any genome so far
+    pub best_score: f32,
+    tracker: InnovationTracker,
+}
+
+impl NeatPopulation {
+    pub fn new(size: usize, input_count: usize, output_count: usize, rng: &mut impl Rng) -> Self {
+        let mut tracker = InnovationTracker::default();
+        let genomes = (0..size)
+            .map(|_| NeatGenome::minimal(input_count, output_count, &mut tracker, rng))
+            .collect();
+        Self {
+            genomes,
+            fitness: vec![0.0; size],
+            species: Vec::new(),
+            best_fitness: 0.0,
+            best_score: 0.0,
+            tracker,
+        }
+    }
+
+    // Result of the bird with the given index
+    pub fn record(&mut self, index: usize, fitness: f32, score: f32) {
+        if let Some(slot) = self.fitness.get_mut(index) {
+            *slot = fitness;
+        }
+        self.best_fitness = self.best_fitness.max(fitness);
+        self.best_score = self.best_score.max(score);
+    }
+
+    // Put every genome into the first species whose representative is close enough,
+    // founding a new species if there is none
+    fn speciate(&mut self, compatibility_threshold: f32) {
+        for species in &mut self.species {
+            species.members.clear();
+        }
+        for (index, genome) in self.genomes.iter().enumerate() {
+            match self.species.iter_mut().find(|species| {
+                species.representative.compatibility(genome) < compatibility_threshold
+            }) {
+                Some(species) => species.members.push(index),
+                None => self.species.push(Species {
+                    representative: genome.clone(),
+                    members: vec![index],
+                }),
+            }
+        }
+        self.species.retain(|species| !species.members.is_empty());
+        for species in &mut self.species {
+            species.representative = self.genomes[species.members[0]].clone();
+        }
+    }
+
+    // Replace the evaluated generation with its offspring. Every species gets a share
+    // of the offspring proportional to its mean fitness, which is fitness sharing.
+    pub fn next_generation(&mut self, settings: &NeatSettings, rng: &mut TrainingRng) {
+        self.speciate(settings.compatibility_threshold);
+        let fitness = &self.fitness;
+        let shares: Vec<f32> = self
+            .species
+            .iter()
+            .map(|species| {
+                species
+                    .members
+                    .iter()
+                    .map(|index| fitness[*index].max(0.0))
+                    .sum::<f32>()
+                    / species.members.len() as f32
+            })
+            .collect();
+        let offspring = offspring_counts(&shares, settings.population_size);
+
+        let mut children = Vec::with_capacity(settings.population_size);
+        for (species, count) in self.species.iter().zip(offspring) {
+            if count == 0 {
+                continue;
+            }
+            let mut members = species.members.clone();
+            members.sort_by(|a, b| fitness[*b].total_cmp(&fitness[*a]));
+            let mut count = count;
+            if members.len() >= CHAMPION_SPECIES_SIZE {
+                children.push(self.genomes[members[0]].clone());
+                count -= 1;
+            }
+            // Only the better half of a species reproduces
+            let parents = &members[..members.len().div_ceil(2)];
+            for _ in 0..count {
+                let first = parents[rng.selection.gen_range(0..parents.len())];
+                let second = parents[rng.selection.gen_range(0..parents.len())];
+                let mut child =
+                    if first != second && rng.crossover.gen::<f32>() < CROSSOVER_PROBABILITY {
+                        let (fitter, other) = if fitness[first] >= fitness[second] {
+                            (first, second)
+                        } else {
+                            (second, first)
+                        };
+                        NeatGenome::crossover(
+                            &self.genomes[fitter],
+                            &self.genomes[other],
+                            &mut rng.crossover,
+                        )
+                    } else {
+                        self.genomes[first].clone()
+                    };
+                child.mutate_weights(
+                    settings.mutation_probability,
+                    settings.mutation_operator,
+                    &mut rng.mutation,
+                );
+                if rng.mutation.gen::<f32>() < settings.add_node_probability {
+                    child.mutate_add_node(&mut self.tracker, &mut rng.mutation);
+                }
+                if rng.mutation.gen::<f32>() < settings.add_connection_probability {
+                    child.mutate_add_connection(&mut self.tracker, &mut rng.mutation);
+                }
+                children.push(child);
+            }
+        }
+        self.fitness = vec![0.0; children.len()];
+        self.genomes = children;
+    }
+}
+
+// Split count between the shares, handing the remainder to the largest fractions.
+// Shares that are all zero are split evenly.
+fn offspring_counts(shares: &[f32], count: usize) -> Vec<usize> {
+    if shares.is_empty() {
+        return Vec::new();
+    }
+    let total: f32 = shares.iter().sum();
+    let exact: Vec<f32> = shares
+        .iter()
+        .map(|share| {
+            if total > 0.0 {
+                share / total * count as f32
+            } else {
+                count as f32 / shares.len() as f32
+            }
+        })
+        .collect();
+    let mut counts: Vec<usize> = exact.iter().map(|share| share.floor() as usize).collect();
+    let mut by_fraction: Vec<usize> = (0..shares.len()).collect();
+    by_fraction.sort_by(|a, b| {
+        (exact[*b] - exact[*b].floor()).total_cmp(&(exact[*a] - exact[*a].floor()))
+    });
+    let assigned: usize = counts.iter().sum();
+    for index in by_fraction
+        .into_iter()
+        .cycle()
+        .take(count.saturating_sub(assigned))
+    {
+        counts[index] += 1;
+    }
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutation::GaussianPerturbation;
+
+    fn connection(innovation: u32, from: u32, to: u32, weight: f32) -> ConnectionGene {
+        ConnectionGene {
+            innovation,
+            from,
+            to,
+            weight,
+            enabled: true,
+        }
+    }
+
+    // A minimal genome of four inputs and one output grown by many structural mutations
+    fn grown_genome(tracker: &mut InnovationTracker, rng: &mut StdRng) -> NeatGenome {
+        let mut genome = NeatGenome::minimal(4, 1, tracker, rng);
+        for _ in 0..30 {
+            if rng.gen_bool(0.3) {
+                genome.mutate_add_node(tracker, rng);
+            }
+            genome.mutate_add_connection(tracker, rng);
+            genome.mutate_weights(0.5, &GaussianPerturbation { sigma: 0.5 }, rng);
+        }
+        genome
+    }
+
+    fn genome_of_seed(seed: u64) -> NeatGenome {
+        grown_genome(
+            &mut InnovationTracker::default(),
+            &mut StdRng::seed_from_u64(seed),
+        )
+    }
+
+    // Evaluate a node straight from the genes, by recursing into its inputs
+    fn node_value(genome: &NeatGenome, id: u32, input: &[f32]) -> f32 {
+        let node = genome.node(id).unwrap();
+        match node.kind {
+            NodeKind::Input => input[id as usize],
+            NodeKind::Bias => 1.0,
+            NodeKind::Hidden | NodeKind::Output => {
+                let sum = genome
+                    .connections
+                    .iter()
+                    .filter(|c| c.enabled && c.to == id)
+                    .map(|c| node_value(genome, c.from, input) * c.weight)
+                    .sum::<f32>();
+                node.activation.apply(sum)
+            }
+        }
+    }
+
+    #[test]
+    fn compatibility_counts_excess_disjoint_and_weight_differences() {
+        let node = |id, kind| NodeGene {
+            id,
+            kind,
+            activation: Activation::Sigmoid,
+        };
+        let nodes = vec![node(0, NodeKind::Input), node(1, NodeKind::Output)];
+        let a = NeatGenome {
+            nodes: nodes.clone(),
+            connections: vec![
+                connection(0, 0, 1, 0.5),
+                connection(1, 0, 1, 1.0),
+                connection(2, 0, 1, 0.0),
+                connection(3, 0, 1, 0.0),
+                connection(5, 0, 1, 0.0),
+            ],
+        };
+        let b = NeatGenome {
+            nodes,
+            connections: vec![
+                connection(0, 0, 1, 1.5),
+                connection(1, 0, 1, 0.0),
+                connection(4, 0, 1, 0.0),
+                connection(6, 0, 1, 0.0),
+                connection(7, 0, 1, 0.0),
+            ],
+        };
+        // 6 and 7 are past the last gene of a, 2, 3, 4 and 5 are disjoint, and the
+        // matching genes 0 and 1 differ by 1 on average
+        let expected =
+            2.0 * EXCESS_COEFFICIENT + 4.0 * DISJOINT_COEFFICIENT + 1.0 * WEIGHT_COEFFICIENT;
+        assert!((a.compatibility(&b) - expected).abs() < 1e-6);
+        assert!((b.compatibility(&a) - expected).abs() < 1e-6);
+        assert_eq!(a.compatibility(&a), 0.0);
+    }
+
+    #[test]
+    fn crossover_inherits_the_structure_of_the_fitter_parent() {
+        // Parents of the same population share their innovation numbers
+        let mut tracker = InnovationTracker::default();
+        let mut rng = StdRng::seed_from_u64(3);
+        let fitter = grown_genome(&mut tracker, &mut rng);
+        let other = grown_genome(&mut tracker, &mut rng);
+        assert_ne!(fitter.connections.len(), other.connections.len());
+        for _ in 0..10 {
+            let child = NeatGenome::crossover(&fitter, &other, &mut rng);
+            assert_eq!(child.nodes, fitter.nodes);
+            assert_eq!(child.connections.len(), fitter.connections.len());
+            for (gene, parent_gene) in child.connections.iter().zip(&fitter.connections) {
+                assert_eq!(
+                    (gene.innovation, gene.from, gene.to),
+                    (parent_gene.innovation, parent_gene.from, parent_gene.to)
+                );
+                // Matching genes take their weight from either parent
+                let matching = other
+                    .connections
+                    .iter()
+                    .find(|c| c.innovation == gene.innovation);
+                assert!(
+                    gene.weight == parent_gene.weight
+                        || matching.is_some_and(|c| c.weight == gene.weight)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn added_connections_never_create_cycles() {
+        for seed in 0..20 {
+            let genome = genome_of_seed(seed);
+            assert!(genome.hidden_node_count() > 0);
+            for connection in &genome.connections {
+                assert!(
+                    !genome.reaches(connection.to, connection.from),
+                    "seed {} has a cycle through {:?}",
+                    seed,
+                    connection
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn compiled_network_matches_the_genes() {
+        for seed in 0..10 {
+            let genome = genome_of_seed(seed);
+            let mut network = genome.compile();
+            let output = genome
+                .nodes
+                .iter()
+                .find(|n| n.kind == NodeKind::Output)
+                .unwrap();
+            for step in 0..5 {
+                let input = [step as f32 - 2.0, 0.5, -1.0, 3.0 * seed as f32];
+                let expected = node_value(&genome, output.id, &input);
+                let compiled = network.forward(&input)[0];
+                assert!(
+                    (compiled - expected).abs() < 1e-5,
+                    "{} vs {}",
+                    compiled,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn offspring_counts_add_up_to_the_population() {
+        for shares in [
+            vec![1.0],
+            vec![1.0, 1.0, 1.0],
+            vec![0.3, 2.7, 0.0, 1.1],
+            vec![0.0, 0.0],
+            vec![5.0, 0.001, 0.001, 0.001],
+        ] {
+            for count in [0, 1, 7, 150, 400] {
+                let counts = offspring_counts(&shares, count);
+                assert_eq!(counts.len(), shares.len());
+                assert_eq!(
+                    counts.iter().sum::<usize>(),
+                    count,
+                    "{:?} of {}",
+                    shares,
+                    count
+                );
+            }
+        }
+        assert_eq!(offspring_counts(&[1.0, 3.0], 100), vec![25, 75]);
+        assert_eq!(offspring_counts(&[0.0, 2.0], 10), vec![0, 10]);
+    }
+}
@@ -1,15 +1,111 @@
+use std::fmt;
+
 use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::initialization::WeightInit;
+use crate::supervised::Loss;
 
 // Define the structure of the Neural Network
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct NeuralNetwork {
     layers: Vec<Layer>,
+    #[serde(default)]
+    weight_init: WeightInit, // Scheme the network, or its first ancestor, was drawn with
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 struct Layer {
     weights: Vec<Vec<f32>>, // Matrix of weights
     biases: Vec<f32>,       // Vector of biases
+    #[serde(default)]
+    activation: Activation, // Applied to every neuron of the layer
+    // Elman layer: weights from the previous outputs of this layer to each neuron.
+    // Empty for a feed-forward layer.
+    #[serde(default)]
+    recurrent_weights: Vec<Vec<f32>>,
+}
+
+// Activation function applied to the output of a neuron
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Activation {
+    #[default]
+    Sigmoid,
+    Tanh,
+    Relu,
+    LeakyRelu,
+    Linear,
+    Step,
+}
+
+impl Activation {
+    pub const ALL: [Activation; 6] = [
+        Activation::Sigmoid,
+        Activation::Tanh,
+        Activation::Relu,
+        Activation::LeakyRelu,
+        Activation::Linear,
+        Activation::Step,
+    ];
+
+    pub fn apply(&self, x: f32) -> f32 {
+        match self {
+            Activation::Sigmoid => sigmoid(x),
+            Activation::Tanh => x.tanh(),
+            Activation::Relu => x.max(0.0),
+            Activation::LeakyRelu => {
+                if x > 0.0 {
+                    x
+                } else {
+                    0.01 * x
+                }
+            }
+            Activation::Linear => x,
+            Activation::Step => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    // Derivative at the weighted sum x, whose activation is y. Step has no useful
+    // gradient, so layers with it are not changed by backpropagation.
+    pub fn derivative(&self, x: f32, y: f32) -> f32 {
+        match self {
+            Activation::Sigmoid => sigmoid_derivative(x),
+            Activation::Tanh => 1.0 - y * y,
+            Activation::Relu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+            Activation::LeakyRelu => {
+                if x > 0.0 {
+                    1.0
+                } else {
+                    0.01
+                }
+            }
+            Activation::Linear => 1.0,
+            Activation::Step => 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Activation::Sigmoid => "Sigmoid",
+            Activation::Tanh => "Tanh",
+            Activation::Relu => "ReLU",
+            Activation::LeakyRelu => "Leaky ReLU",
+            Activation::Linear => "Linear",
+            Activation::Step => "Step",
+        }
+    }
 }
 
 // Activation function (Sigmoid)
@@ -17,113 +113,615 @@ fn sigmoid(x: f32) -> f32 {
     1.0 / (1.0 + (-x).exp())
 }
 
-// Derivative of the sigmoid function (used later for backpropagation) NOT USED
-fn _sigmoid_derivative(x: f32) -> f32 {
+// Shape of a network: neurons per layer starting with the inputs, and the activation of
+// every layer after the inputs and whether it is recurrent
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Topology {
+    pub layer_sizes: Vec<usize>,
+    pub activations: Vec<Activation>,
+    pub recurrent: Vec<bool>,
+}
+
+impl Topology {
+    // Layers without an entry in activations use Sigmoid, layers without an entry in
+    // recurrent are feed-forward
+    pub fn new(layer_sizes: &[usize], activations: &[Activation], recurrent: &[bool]) -> Self {
+        let layer_count = layer_sizes.len().saturating_sub(1);
+        Self {
+            layer_sizes: layer_sizes.to_vec(),
+            activations: (0..layer_count)
+                .map(|layer| activations.get(layer).copied().unwrap_or_default())
+                .collect(),
+            recurrent: (0..layer_count)
+                .map(|layer| recurrent.get(layer).copied().unwrap_or(false))
+                .collect(),
+        }
+    }
+
+    // Networks with the same layers can exchange genes, whatever their activations
+    pub fn is_compatible(&self, other: &Topology) -> bool {
+        self.layer_sizes == other.layer_sizes && self.recurrent == other.recurrent
+    }
+
+    // Number of weights and biases of a network with this topology
+    pub fn parameter_count(&self) -> usize {
+        self.layer_sizes
+            .windows(2)
+            .zip(&self.recurrent)
+            .map(|(sizes, recurrent)| {
+                let recurrent_weights = if *recurrent { sizes[1] * sizes[1] } else { 0 };
+                sizes[0] * sizes[1] + recurrent_weights + sizes[1]
+            })
+            .sum()
+    }
+}
+
+// Layer sizes with recurrent layers marked, e.g. [4, 3r, 1]
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        for (index, size) in self.layer_sizes.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", size)?;
+            if index > 0 && self.recurrent[index - 1] {
+                write!(f, "r")?;
+            }
+        }
+        write!(f, "]")
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NetworkError {
+    TopologyMismatch {
+        expected: Box<Topology>,
+        found: Box<Topology>,
+    },
+    Inconsistent(Vec<usize>),
+    GenomeLength {
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkError::TopologyMismatch { expected, found } => write!(
+                f,
+                "network with layers {} cannot be combined with one with layers {}",
+                found, expected
+            ),
+            NetworkError::Inconsistent(layer_sizes) => write!(
+                f,
+                "weights of the network do not match its layer sizes {:?}",
+                layer_sizes
+            ),
+            NetworkError::GenomeLength { expected, found } => write!(
+                f,
+                "genome has {} parameters but the topology needs {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl std::error::Error for NetworkError {}
+
+// Derivative of the sigmoid function, used by backpropagation
+fn sigmoid_derivative(x: f32) -> f32 {
     let s = sigmoid(x);
     s * (1.0 - s)
 }
 
 impl NeuralNetwork {
-    // Initialize a new Neural Network
-    pub fn new(sizes: &[usize]) -> Self {
-        let mut rng = rand::thread_rng();
-
+    // Initialize a new Neural Network. Layers without an entry in activations use Sigmoid.
+    pub fn new(
+        sizes: &[usize],
+        activations: &[Activation],
+        weight_init: WeightInit,
+        rng: &mut impl Rng,
+    ) -> Self {
         let layers = sizes
             .windows(2)
-            .map(|sizes| {
+            .enumerate()
+            .map(|(index, sizes)| {
                 let (in_size, out_size) = (sizes[0], sizes[1]);
 
                 let weights = (0..out_size)
                     .map(|_| {
                         (0..in_size)
-                            .map(|_| rng.gen::<f32>() * 2.0 - 1.0) // random values between -1 and 1
+                            .map(|_| weight_init.weight(in_size, out_size, rng))
                             .collect()
                     })
                     .collect();
 
-                let biases = (0..out_size)
-                    .map(|_| rng.gen::<f32>() * 2.0 - 1.0)
+                let biases = (0..out_size).map(|_| weight_init.bias(rng)).collect();
+
+                Layer {
+                    weights,
+                    biases,
+                    activation: activations.get(index).copied().unwrap_or_default(),
+                    recurrent_weights: Vec::new(),
+                }
+            })
+            .collect();
+
+        NeuralNetwork {
+            layers,
+            weight_init,
+        }
+    }
+
+    pub fn from_topology(topology: &Topology, weight_init: WeightInit, rng: &mut impl Rng) -> Self {
+        let mut network = Self::new(
+            &topology.layer_sizes,
+            &topology.activations,
+            weight_init,
+            rng,
+        );
+        for (layer, recurrent) in network.layers.iter_mut().zip(&topology.recurrent) {
+            if *recurrent {
+                let size = layer.biases.len();
+                layer.recurrent_weights = (0..size)
+                    .map(|_| {
+                        (0..size)
+                            .map(|_| weight_init.weight(size, size, rng))
+                            .collect()
+                    })
                     .collect();
+            }
+        }
+        network
+    }
+
+    pub fn weight_init(&self) -> WeightInit {
+        self.weight_init
+    }
+
+    // Record the scheme the network descends from, e.g. after building it from a genome
+    pub fn with_weight_init(mut self, weight_init: WeightInit) -> Self {
+        self.weight_init = weight_init;
+        self
+    }
 
-                Layer { weights, biases }
+    // Network of the given topology with its parameters taken from genome, in the order of
+    // genes. Fails if genome does not have exactly topology.parameter_count() values.
+    pub fn from_genome(topology: &Topology, genome: &[f32]) -> Result<Self, NetworkError> {
+        let expected = topology.parameter_count();
+        if genome.len() != expected {
+            return Err(NetworkError::GenomeLength {
+                expected,
+                found: genome.len(),
+            });
+        }
+        let layers = topology
+            .layer_sizes
+            .windows(2)
+            .zip(topology.activations.iter().zip(&topology.recurrent))
+            .map(|(sizes, (activation, recurrent))| {
+                let (in_size, out_size) = (sizes[0], sizes[1]);
+                Layer {
+                    weights: vec![vec![0.0; in_size]; out_size],
+                    biases: vec![0.0; out_size],
+                    activation: *activation,
+                    recurrent_weights: if *recurrent {
+                        vec![vec![0.0; out_size]; out_size]
+                    } else {
+                        Vec::new()
+                    },
+                }
             })
             .collect();
+        let mut network = NeuralNetwork {
+            layers,
+            weight_init: WeightInit::default(),
+        };
+        for (gene, value) in network.genes_mut().zip(genome) {
+            *gene = *value;
+        }
+        Ok(network)
+    }
 
-        NeuralNetwork { layers }
+    // Every parameter as a plain vector, in the order of genes
+    pub fn to_genome(&self) -> Vec<f32> {
+        self.genes().copied().collect()
     }
 
-    // Forward propagation
-    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
-        let mut input = input.to_vec();
+    pub fn topology(&self) -> Topology {
+        Topology {
+            layer_sizes: self.layer_sizes(),
+            activations: self.activations(),
+            recurrent: self.recurrent_layers(),
+        }
+    }
 
-        for layer in &self.layers {
-            input = layer.forward(&input);
+    // Ok if the genes of other line up with the genes of this network
+    pub fn check_compatible(&self, other: &NeuralNetwork) -> Result<(), NetworkError> {
+        let (topology, other_topology) = (self.topology(), other.topology());
+        if topology.is_compatible(&other_topology) {
+            Ok(())
+        } else {
+            Err(NetworkError::TopologyMismatch {
+                expected: Box::new(topology),
+                found: Box::new(other_topology),
+            })
         }
+    }
 
-        input
+    pub fn recurrent_layers(&self) -> Vec<bool> {
+        self.layers
+            .iter()
+            .map(|layer| !layer.recurrent_weights.is_empty())
+            .collect()
     }
-}
 
-impl Layer {
-    // Forward propagation for a layer
-    pub fn forward(&self, input: &[f32]) -> Vec<f32> {
-        self.weights
+    // Turn layers into Elman layers or back into feed-forward layers. New recurrent
+    // weights are zero, so the network behaves as before until they mutate.
+    pub fn set_recurrent_layers(&mut self, recurrent: &[bool]) {
+        for (layer, recurrent) in self.layers.iter_mut().zip(recurrent) {
+            let size = layer.biases.len();
+            match (*recurrent, layer.recurrent_weights.is_empty()) {
+                (true, true) => layer.recurrent_weights = vec![vec![0.0; size]; size],
+                (false, false) => layer.recurrent_weights.clear(),
+                _ => {}
+            }
+        }
+    }
+
+    // Length of the hidden state: the previous outputs of every recurrent layer
+    pub fn hidden_state_size(&self) -> usize {
+        self.layers
             .iter()
-            .zip(&self.biases)
-            .map(|(weights, bias)| {
-                let sum = weights.iter().zip(input).map(|(w, i)| w * i).sum::<f32>() + bias;
-                sigmoid(sum)
+            .filter(|layer| !layer.recurrent_weights.is_empty())
+            .map(|layer| layer.biases.len())
+            .sum()
+    }
+
+    pub fn check_consistent(&self) -> Result<(), NetworkError> {
+        if self.is_consistent() {
+            Ok(())
+        } else {
+            Err(NetworkError::Inconsistent(self.layer_sizes()))
+        }
+    }
+
+    pub fn activations(&self) -> Vec<Activation> {
+        self.layers.iter().map(|layer| layer.activation).collect()
+    }
+
+    // Layers without an entry in activations keep their current activation
+    pub fn set_activations(&mut self, activations: &[Activation]) {
+        for (layer, activation) in self.layers.iter_mut().zip(activations) {
+            layer.activation = *activation;
+        }
+    }
+
+    // Weights, recurrent weights and biases of every layer, for code that lays networks out
+    // differently
+    pub fn layer_parameters(&self) -> impl Iterator<Item = LayerParameters<'_>> {
+        self.layers.iter().map(|layer| LayerParameters {
+            weights: &layer.weights,
+            recurrent_weights: &layer.recurrent_weights,
+            biases: &layer.biases,
+        })
+    }
+
+    // Every weight and bias, layer by layer with the weights of a layer, then its
+    // recurrent weights, then its biases
+    pub fn genes(&self) -> impl Iterator<Item = &f32> {
+        self.layers.iter().flat_map(|layer| {
+            layer
+                .weights
+                .iter()
+                .flatten()
+                .chain(layer.recurrent_weights.iter().flatten())
+                .chain(layer.biases.iter())
+        })
+    }
+
+    // Same order as genes
+    pub fn genes_mut(&mut self) -> impl Iterator<Item = &mut f32> {
+        self.layers.iter_mut().flat_map(|layer| {
+            layer
+                .weights
+                .iter_mut()
+                .flatten()
+                .chain(layer.recurrent_weights.iter_mut().flatten())
+                .chain(layer.biases.iter_mut())
+        })
+    }
+
+    // Make the network respond to an input x the way it responded to (x - mean) / deviation,
+    // by folding the standardization into the weights and biases of the first layer
+    pub fn fold_input_standardization(&mut self, mean: &[f32], deviation: &[f32]) {
+        let Some(layer) = self.layers.first_mut() else {
+            return;
+        };
+        for (weights, bias) in layer.weights.iter_mut().zip(&mut layer.biases) {
+            for ((weight, mean), deviation) in weights.iter_mut().zip(mean).zip(deviation) {
+                *weight /= deviation;
+                *bias -= *weight * mean;
+            }
+        }
+    }
+
+    // Take the weights and biases of a whole layer from a network of the same shape
+    pub fn copy_layer(&mut self, from: &NeuralNetwork, layer: usize) {
+        let (to, from) = (&mut self.layers[layer], &from.layers[layer]);
+        to.weights.clone_from(&from.weights);
+        to.recurrent_weights.clone_from(&from.recurrent_weights);
+        to.biases.clone_from(&from.biases);
+    }
+
+    // Take the incoming weights and bias of one neuron from a network of the same shape
+    pub fn copy_neuron(&mut self, from: &NeuralNetwork, layer: usize, neuron: usize) {
+        let (to, from) = (&mut self.layers[layer], &from.layers[layer]);
+        to.weights[neuron].clone_from(&from.weights[neuron]);
+        if let Some(row) = from.recurrent_weights.get(neuron) {
+            to.recurrent_weights[neuron].clone_from(row);
+        }
+        to.biases[neuron] = from.biases[neuron];
+    }
+
+    // Number of neurons in each layer, starting with the inputs
+    pub fn layer_sizes(&self) -> Vec<usize> {
+        let mut sizes = Vec::with_capacity(self.layers.len() + 1);
+        if let Some(first) = self.layers.first() {
+            sizes.push(first.weights.first().map_or(0, |row| row.len()));
+        }
+        for layer in &self.layers {
+            sizes.push(layer.biases.len());
+        }
+        sizes
+    }
+
+    // True if every weight row and bias vector agrees with the layer sizes
+    pub fn is_consistent(&self) -> bool {
+        let sizes = self.layer_sizes();
+        self.layers
+            .iter()
+            .zip(sizes.windows(2))
+            .all(|(layer, sizes)| {
+                layer.weights.len() == sizes[1]
+                    && layer.weights.iter().all(|row| row.len() == sizes[0])
+                    && (layer.recurrent_weights.is_empty()
+                        || (layer.recurrent_weights.len() == sizes[1]
+                            && layer
+                                .recurrent_weights
+                                .iter()
+                                .all(|row| row.len() == sizes[1])))
             })
-            .collect()
     }
-}
 
-// average of two parents
-pub fn crossover_average(parent1: NeuralNetwork, parent2: NeuralNetwork) -> NeuralNetwork {
-    let mut child = parent1.clone();
-    for (layer_child, (layer_p1, layer_p2)) in child
-        .layers
-        .iter_mut()
-        .zip(parent1.layers.iter().zip(&parent2.layers))
-    {
-        for (weight_child, (weight_p1, weight_p2)) in layer_child
-            .weights
-            .iter_mut()
-            .zip(layer_p1.weights.iter().zip(&layer_p2.weights))
-        {
-            // Average weights
-            for (w_child, (w_p1, w_p2)) in
-                weight_child.iter_mut().zip(weight_p1.iter().zip(weight_p2))
-            {
-                *w_child = (*w_p1 + *w_p2) / 2.0;
+    // Forward propagation of a single network. hidden_state holds the previous outputs of
+    // the recurrent layers and is updated. Whole populations are evaluated with
+    // batch::BatchedPopulation instead.
+    pub fn forward(&self, input: &[f32], hidden_state: &mut [f32]) -> Vec<f32> {
+        let mut input = input.to_vec();
+        let mut state_offset = 0;
+
+        for layer in &self.layers {
+            let state_size = if layer.recurrent_weights.is_empty() {
+                0
+            } else {
+                layer.biases.len()
+            };
+            let state = &mut hidden_state[state_offset..state_offset + state_size];
+            input = layer.forward(&input, state);
+            state_offset += state_size;
+        }
+
+        input
+    }
+
+    // Add the gradient of the loss of one sample to gradient, which is in the order of
+    // genes, and return the loss. Samples are independent, so recurrent layers start from
+    // a zero hidden state and their recurrent weights get no gradient.
+    pub fn backpropagate(
+        &self,
+        input: &[f32],
+        target: &[f32],
+        loss: Loss,
+        gradient: &mut [f32],
+    ) -> f32 {
+        // Forward pass keeping the input and the weighted sums of every layer
+        let mut inputs = vec![input.to_vec()];
+        let mut sums = Vec::with_capacity(self.layers.len());
+        for (index, layer) in self.layers.iter().enumerate() {
+            let sum: Vec<f32> = layer
+                .weights
+                .iter()
+                .zip(&layer.biases)
+                .map(|(weights, bias)| {
+                    weights
+                        .iter()
+                        .zip(&inputs[index])
+                        .map(|(w, i)| w * i)
+                        .sum::<f32>()
+                        + bias
+                })
+                .collect();
+            inputs.push(sum.iter().map(|x| layer.activation.apply(*x)).collect());
+            sums.push(sum);
+        }
+        let (Some(output), Some(last)) = (inputs.pop(), self.layers.last()) else {
+            return 0.0;
+        };
+        let value = loss.value(&output, target);
+        let mut delta = loss.output_delta(last.activation, &sums[sums.len() - 1], &output, target);
+
+        // Backward pass, from the output layer to the first hidden layer
+        let mut end = gradient.len();
+        for (index, layer) in self.layers.iter().enumerate().rev() {
+            let input = &inputs[index];
+            let weight_count: usize = layer.weights.iter().map(Vec::len).sum();
+            let recurrent_count: usize = layer.recurrent_weights.iter().map(Vec::len).sum();
+            let start = end - weight_count - recurrent_count - layer.biases.len();
+            let genes = &mut gradient[start..end];
+            for (neuron, d) in delta.iter().enumerate() {
+                let weights = &mut genes[neuron * input.len()..(neuron + 1) * input.len()];
+                for (g, i) in weights.iter_mut().zip(input) {
+                    *g += d * i;
+                }
             }
-            // Average biases
-            for (bias_child, (bias_p1, bias_p2)) in layer_child
-                .biases
+            for (g, d) in genes[weight_count + recurrent_count..]
                 .iter_mut()
-                .zip(layer_p1.biases.iter().zip(&layer_p2.biases))
+                .zip(&delta)
             {
-                *bias_child = (*bias_p1 + *bias_p2) / 2.0;
+                *g += d;
+            }
+            end = start;
+            if index > 0 {
+                let previous = &self.layers[index - 1];
+                delta = (0..input.len())
+                    .map(|j| {
+                        let error: f32 = layer
+                            .weights
+                            .iter()
+                            .zip(&delta)
+                            .map(|(weights, d)| weights[j] * d)
+                            .sum();
+                        error * previous.activation.derivative(sums[index - 1][j], input[j])
+                    })
+                    .collect();
             }
         }
+        value
     }
-    child
 }
 
-pub fn mutate(nn: &mut NeuralNetwork, mutation_probability: f32, mutation_rate: f32) {
-    let mut rng = rand::thread_rng();
-    let should_mutate = rng.gen::<f32>() < mutation_probability;
-    for layer in &mut nn.layers {
-        for weight in &mut layer.weights {
-            for w in weight.iter_mut() {
-                if should_mutate {
-                    *w += rng.gen_range(-mutation_rate..mutation_rate);
+// Parameters of one layer, see NeuralNetwork::layer_parameters
+pub struct LayerParameters<'a> {
+    pub weights: &'a [Vec<f32>],
+    pub recurrent_weights: &'a [Vec<f32>], // Empty for a feed-forward layer
+    pub biases: &'a [f32],
+}
+
+impl Layer {
+    // Forward propagation for a layer. state holds the previous outputs of a recurrent
+    // layer and is empty for a feed-forward layer.
+    pub fn forward(&self, input: &[f32], state: &mut [f32]) -> Vec<f32> {
+        let output: Vec<f32> = self
+            .weights
+            .iter()
+            .zip(&self.biases)
+            .enumerate()
+            .map(|(neuron, (weights, bias))| {
+                let mut sum = weights.iter().zip(input).map(|(w, i)| w * i).sum::<f32>() + bias;
+                if let Some(recurrent) = self.recurrent_weights.get(neuron) {
+                    sum += recurrent
+                        .iter()
+                        .zip(&*state)
+                        .map(|(w, s)| w * s)
+                        .sum::<f32>();
                 }
+                self.activation.apply(sum)
+            })
+            .collect();
+        if !state.is_empty() {
+            state.copy_from_slice(&output);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recurrent_topology() -> Topology {
+        Topology::new(
+            &[4, 3, 2, 1],
+            &[Activation::Tanh, Activation::LeakyRelu, Activation::Sigmoid],
+            &[true, false, true],
+        )
+    }
+
+    #[test]
+    fn genome_round_trips() {
+        let topology = recurrent_topology();
+        let mut rng = StdRng::seed_from_u64(5);
+        let network = NeuralNetwork::from_topology(&topology, WeightInit::Xavier, &mut rng);
+        let genome = network.to_genome();
+        assert_eq!(genome.len(), topology.parameter_count());
+
+        let rebuilt = NeuralNetwork::from_genome(&topology, &genome)
+            .unwrap()
+            .with_weight_init(WeightInit::Xavier);
+        assert_eq!(rebuilt, network);
+        assert_eq!(rebuilt.to_genome(), genome);
+    }
+
+    #[test]
+    fn genome_of_the_wrong_length_is_rejected() {
+        let topology = recurrent_topology();
+        let genome = vec![0.5; topology.parameter_count() - 1];
+        match NeuralNetwork::from_genome(&topology, &genome) {
+            Err(NetworkError::GenomeLength { expected, found }) => {
+                assert_eq!(
+                    (expected, found),
+                    (topology.parameter_count(), genome.len())
+                );
             }
+            other => panic!("expected a genome length error, got {:?}", other),
         }
-        for bias in layer.biases.iter_mut() {
-            if should_mutate {
-                *bias += rng.gen_range(-mutation_rate..mutation_rate);
+    }
+
+    // Loss of one sample, as returned by backpropagate
+    fn loss_of(
+        topology: &Topology,
+        genome: &[f32],
+        input: &[f32],
+        target: &[f32],
+        loss: Loss,
+    ) -> f32 {
+        let network = NeuralNetwork::from_genome(topology, genome).unwrap();
+        let mut gradient = vec![0.0; genome.len()];
+        network.backpropagate(input, target, loss, &mut gradient)
+    }
+
+    #[test]
+    fn backpropagation_matches_finite_differences() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let input = [0.3, -0.7, 0.5, 0.1];
+        let target = [0.7];
+        let cases = [
+            (vec![Activation::Sigmoid; 3], Loss::BinaryCrossEntropy),
+            (
+                vec![Activation::Tanh, Activation::LeakyRelu, Activation::Sigmoid],
+                Loss::MeanSquaredError,
+            ),
+            (
+                vec![Activation::Linear, Activation::Tanh, Activation::Sigmoid],
+                Loss::MeanSquaredError,
+            ),
+        ];
+        for (activations, loss) in cases {
+            let topology = Topology::new(&[4, 3, 2, 1], &activations, &[true, false, false]);
+            let network = NeuralNetwork::from_topology(&topology, WeightInit::Uniform, &mut rng);
+            let genome = network.to_genome();
+            let mut gradient = vec![0.0; genome.len()];
+            network.backpropagate(&input, &target, loss, &mut gradient);
+
+            let step = 1e-3;
+            for gene in 0..genome.len() {
+                let (mut above, mut below) = (genome.clone(), genome.clone());
+                above[gene] += step;
+                below[gene] -= step;
+                let numeric = (loss_of(&topology, &above, &input, &target, loss)
+                    - loss_of(&topology, &below, &input, &target, loss))
+                    / (2.0 * step);
+                assert!(
+                    (numeric - gradient[gene]).abs() < 1e-3,
+                    "{:?} gene {}: backpropagation {} but finite difference {}",
+                    loss,
+                    gene,
+                    gradient[gene],
+                    numeric
+                );
             }
         }
     }
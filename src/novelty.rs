@@ -0,0 +1,144 @@
+// Novelty search (Lehman and Stanley 2011). Birds are rewarded for flying differently
+// from the rest of the generation and from an archive of earlier novel flights, so the
+// population keeps exploring instead of collapsing onto the first strategy that works.
+use bevy::prelude::*;
+
+use crate::selection::Individual;
+use crate::simulation::{BirdState, FIXED_TIMESTEP, HEIGHT_SAMPLES, WINDOW_HEIGHT};
+
+pub const NOVELTY_NEIGHBOURS: usize = 15; // Nearest behaviours averaged into the novelty
+pub const ARCHIVE_ADDITIONS: usize = 5; // Most novel behaviours archived every generation
+pub const ARCHIVE_CAPACITY: usize = 2000; // Oldest behaviours are dropped beyond this
+
+// What parents are selected by in the genetic algorithm
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionObjective {
+    #[default]
+    Fitness,
+    Novelty,
+    Blend,  // Weighted sum of normalized novelty and fitness
+    Pareto, // NSGA-II rank over survival time, pipes passed and flap efficiency
+}
+
+impl SelectionObjective {
+    pub const ALL: [SelectionObjective; 4] = [
+        SelectionObjective::Fitness,
+        SelectionObjective::Novelty,
+        SelectionObjective::Blend,
+        SelectionObjective::Pareto,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionObjective::Fitness => "Fitness",
+            SelectionObjective::Novelty => "Novelty",
+            SelectionObjective::Blend => "Novelty + Fitness",
+            SelectionObjective::Pareto => "Pareto Front (NSGA-II)",
+        }
+    }
+}
+
+// Behaviour descriptor of a flight: the sampled heights scaled to -1..1, held at the
+// last height once the bird has crashed, followed by the share of steps it flapped in
+pub fn behaviour(state: &BirdState) -> Vec<f32> {
+    let last = state.heights.last().copied().unwrap_or(state.y);
+    let mut descriptor: Vec<f32> = (0..HEIGHT_SAMPLES)
+        .map(|sample| state.heights.get(sample).copied().unwrap_or(last) / (WINDOW_HEIGHT / 2.0))
+        .collect();
+    let flap_rate = if state.fitness > 0.0 {
+        state.flaps as f32 * FIXED_TIMESTEP / state.fitness
+    } else {
+        0.0
+    };
+    descriptor.push(flap_rate);
+    descriptor
+}
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f32>()
+        .sqrt()
+}
+
+// Behaviours of earlier generations that were novel when they were flown
+#[derive(Clone, Debug, Default, Resource)]
+pub struct NoveltyArchive {
+    behaviours: Vec<Vec<f32>>,
+}
+
+impl NoveltyArchive {
+    pub fn archived_count(&self) -> usize {
+        self.behaviours.len()
+    }
+
+    // Mean distance of every individual's behaviour to its nearest neighbours among the
+    // population and the archive
+    pub fn novelty(&self, population: &[Individual]) -> Vec<f32> {
+        population
+            .iter()
+            .enumerate()
+            .map(|(index, individual)| {
+                let mut distances: Vec<f32> = population
+                    .iter()
+                    .enumerate()
+                    .filter(|(other, _)| *other != index)
+                    .map(|(_, other)| &other.behaviour)
+                    .chain(self.behaviours.iter())
+                    .map(|other| distance(&individual.behaviour, other))
+                    .collect();
+                let neighbours = NOVELTY_NEIGHBOURS.min(distances.len());
+                if neighbours == 0 {
+                    return 0.0;
+                }
+                distances.select_nth_unstable_by(neighbours - 1, f32::total_cmp);
+                distances[..neighbours].iter().sum::<f32>() / neighbours as f32
+            })
+            .collect()
+    }
+
+    // Replace the fitness of every individual with the objective parents are selected
+    // by, and archive the most novel behaviours of the population
+    pub fn score(
+        &mut self,
+        population: &mut [Individual],
+        objective: SelectionObjective,
+        novelty_weight: f32,
+    ) {
+        if !matches!(
+            objective,
+            SelectionObjective::Novelty | SelectionObjective::Blend
+        ) || population.is_empty()
+        {
+            return;
+        }
+        let novelty = self.novelty(population);
+        let mut order: Vec<usize> = (0..population.len()).collect();
+        order.sort_by(|a, b| novelty[*b].total_cmp(&novelty[*a]));
+        self.behaviours.extend(
+            order
+                .iter()
+                .take(ARCHIVE_ADDITIONS)
+                .map(|index| population[*index].behaviour.clone()),
+        );
+        let excess = self.behaviours.len().saturating_sub(ARCHIVE_CAPACITY);
+        self.behaviours.drain(..excess);
+
+        let most_novel = novelty.iter().copied().fold(f32::EPSILON, f32::max);
+        let fittest = population
+            .iter()
+            .map(|individual| individual.fitness)
+            .fold(f32::EPSILON, f32::max);
+        for (individual, novelty) in population.iter_mut().zip(novelty) {
+            individual.fitness = match objective {
+                SelectionObjective::Fitness | SelectionObjective::Pareto => individual.fitness,
+                SelectionObjective::Novelty => novelty,
+                SelectionObjective::Blend => {
+                    novelty_weight * novelty / most_novel
+                        + (1.0 - novelty_weight) * individual.fitness / fittest
+                }
+            };
+        }
+    }
+}
@@ -0,0 +1,179 @@
+// Natural evolution strategy in the style of Salimans et al. 2017, "Evolution Strategies
+// as a Scalable Alternative to Reinforcement Learning". Birds fly mirrored Gaussian
+// perturbations of a central genome, and the rank-shaped fitness of each pair estimates
+// the gradient the centre follows.
+use rand::prelude::*;
+use rand_distr::StandardNormal;
+
+use crate::gui::GuiParameters;
+use crate::optimizer::ParameterOptimizer;
+
+// How the estimated gradient moves the centre
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GradientOptimizer {
+    #[default]
+    SgdMomentum,
+    Adam,
+}
+
+impl GradientOptimizer {
+    pub const ALL: [GradientOptimizer; 2] =
+        [GradientOptimizer::SgdMomentum, GradientOptimizer::Adam];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GradientOptimizer::SgdMomentum => "SGD with Momentum",
+            GradientOptimizer::Adam => "Adam",
+        }
+    }
+}
+
+pub const ADAM_BETA1: f32 = 0.9;
+pub const ADAM_BETA2: f32 = 0.999;
+pub const ADAM_EPSILON: f32 = 1e-8;
+
+pub struct OpenAiEs {
+    centre: Vec<f32>,
+    sigma: f32,
+    learning_rate: f32,
+    momentum: f32, // Only used by SGD
+    optimizer: GradientOptimizer,
+    population_size: usize,
+    noise: Vec<Vec<f32>>, // One vector per mirrored pair of the last ask
+    first_moment: Vec<f32>,
+    second_moment: Vec<f32>, // Only used by Adam
+    steps: i32,
+    gradient_norm: f32,
+}
+
+impl OpenAiEs {
+    pub fn new(
+        start: &[f32],
+        sigma: f32,
+        learning_rate: f32,
+        momentum: f32,
+        optimizer: GradientOptimizer,
+        population_size: usize,
+    ) -> Self {
+        Self {
+            centre: start.to_vec(),
+            sigma: sigma.max(f32::EPSILON),
+            learning_rate,
+            momentum,
+            optimizer,
+            population_size: population_size.max(2),
+            noise: Vec::new(),
+            first_moment: vec![0.0; start.len()],
+            second_moment: vec![0.0; start.len()],
+            steps: 0,
+            gradient_norm: 0.0,
+        }
+    }
+}
+
+impl ParameterOptimizer for OpenAiEs {
+    // Pairs centre + sigma * noise and centre - sigma * noise, followed by the centre
+    // itself when the population size is odd
+    fn ask(&mut self, rng: &mut StdRng) -> Vec<Vec<f32>> {
+        self.noise = (0..self.population_size / 2)
+            .map(|_| {
+                (0..self.centre.len())
+                    .map(|_| rng.sample(StandardNormal))
+                    .collect()
+            })
+            .collect();
+        let mut genomes = Vec::with_capacity(self.population_size);
+        for noise in &self.noise {
+            for sign in [1.0, -1.0] {
+                genomes.push(
+                    self.centre
+                        .iter()
+                        .zip(noise)
+                        .map(|(c, e)| c + sign * self.sigma * e)
+                        .collect(),
+                );
+            }
+        }
+        if genomes.len() < self.population_size {
+            genomes.push(self.centre.clone());
+        }
+        genomes
+    }
+
+    fn tell(&mut self, fitness: &[f32]) {
+        if fitness.len() < self.noise.len() * 2 || self.noise.is_empty() {
+            return;
+        }
+        // Centred ranks in -0.5..=0.5 make the update independent of the fitness scale
+        let evaluated = self.noise.len() * 2;
+        let mut order: Vec<usize> = (0..evaluated).collect();
+        order.sort_by(|a, b| fitness[*a].total_cmp(&fitness[*b]));
+        let mut shaped = vec![0.0; evaluated];
+        for (rank, index) in order.into_iter().enumerate() {
+            shaped[index] = rank as f32 / (evaluated - 1) as f32 - 0.5;
+        }
+        let scale = 1.0 / (evaluated as f32 * self.sigma);
+        let mut gradient = vec![0.0; self.centre.len()];
+        for (pair, noise) in self.noise.iter().enumerate() {
+            let difference = shaped[2 * pair] - shaped[2 * pair + 1];
+            for (g, e) in gradient.iter_mut().zip(noise) {
+                *g += scale * difference * e;
+            }
+        }
+        self.gradient_norm = gradient.iter().map(|g| g * g).sum::<f32>().sqrt();
+
+        // Ascend the gradient
+        self.steps += 1;
+        match self.optimizer {
+            GradientOptimizer::SgdMomentum => {
+                for ((c, v), g) in self
+                    .centre
+                    .iter_mut()
+                    .zip(self.first_moment.iter_mut())
+                    .zip(&gradient)
+                {
+                    *v = self.momentum * *v + (1.0 - self.momentum) * g;
+                    *c += self.learning_rate * *v;
+                }
+            }
+            GradientOptimizer::Adam => {
+                let step_size = self.learning_rate * (1.0 - ADAM_BETA2.powi(self.steps)).sqrt()
+                    / (1.0 - ADAM_BETA1.powi(self.steps));
+                for (((c, m), v), g) in self
+                    .centre
+                    .iter_mut()
+                    .zip(self.first_moment.iter_mut())
+                    .zip(self.second_moment.iter_mut())
+                    .zip(&gradient)
+                {
+                    *m = ADAM_BETA1 * *m + (1.0 - ADAM_BETA1) * g;
+                    *v = ADAM_BETA2 * *v + (1.0 - ADAM_BETA2) * g * g;
+                    *c += step_size * *m / (v.sqrt() + ADAM_EPSILON);
+                }
+            }
+        }
+    }
+
+    // Sigma applies from the next ask, the rest from the next update. Switching the
+    // optimizer starts its moments from zero.
+    fn configure(&mut self, params: &GuiParameters) {
+        self.sigma = params.es_sigma.max(f32::EPSILON);
+        self.learning_rate = params.es_learning_rate;
+        self.momentum = params.es_momentum;
+        if self.optimizer != params.es_optimizer {
+            self.optimizer = params.es_optimizer;
+            self.first_moment.fill(0.0);
+            self.second_moment.fill(0.0);
+            self.steps = 0;
+        }
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "{} | update {} | gradient norm {:.4}",
+            self.optimizer.name(),
+            self.steps,
+            self.gradient_norm
+        )
+    }
+}
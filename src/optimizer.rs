@@ -0,0 +1,152 @@
+// Training algorithms that treat a network as a plain parameter vector, see
+// NeuralNetwork::to_genome. The genetic algorithm in systems works on networks directly.
+use std::str::FromStr;
+
+use bevy::prelude::*;
+use rand::rngs::StdRng;
+
+use crate::gui::GuiParameters;
+use crate::initialization::WeightInit;
+use crate::neural_network::Topology;
+
+// Proposes the genomes of each generation and learns from the fitness they reach
+pub trait ParameterOptimizer: Send + Sync {
+    // Genomes of the next generation, one per bird
+    fn ask(&mut self, rng: &mut StdRng) -> Vec<Vec<f32>>;
+    // Fitness of every genome of the last ask, in the same order. Higher is better.
+    fn tell(&mut self, fitness: &[f32]);
+    // Take over settings that may change while the search runs, before every ask
+    fn configure(&mut self, _params: &GuiParameters) {}
+    // One line about the state of the search, for the GUI
+    fn status(&self) -> String;
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrainingAlgorithm {
+    #[default]
+    Genetic,
+    CmaEs,
+    OpenAiEs,
+    DifferentialEvolution,
+    ParticleSwarm,
+}
+
+impl TrainingAlgorithm {
+    pub const ALL: [TrainingAlgorithm; 5] = [
+        TrainingAlgorithm::Genetic,
+        TrainingAlgorithm::CmaEs,
+        TrainingAlgorithm::OpenAiEs,
+        TrainingAlgorithm::DifferentialEvolution,
+        TrainingAlgorithm::ParticleSwarm,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TrainingAlgorithm::Genetic => "Genetic Algorithm",
+            TrainingAlgorithm::CmaEs => "CMA-ES",
+            TrainingAlgorithm::OpenAiEs => "OpenAI-ES",
+            TrainingAlgorithm::DifferentialEvolution => "Differential Evolution",
+            TrainingAlgorithm::ParticleSwarm => "Particle Swarm",
+        }
+    }
+
+    // Value of the --algorithm command line flag
+    pub fn flag(&self) -> &'static str {
+        match self {
+            TrainingAlgorithm::Genetic => "ga",
+            TrainingAlgorithm::CmaEs => "cma-es",
+            TrainingAlgorithm::OpenAiEs => "openai-es",
+            TrainingAlgorithm::DifferentialEvolution => "de",
+            TrainingAlgorithm::ParticleSwarm => "pso",
+        }
+    }
+}
+
+impl FromStr for TrainingAlgorithm {
+    type Err = ();
+
+    fn from_str(flag: &str) -> Result<Self, Self::Err> {
+        TrainingAlgorithm::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.flag() == flag)
+            .ok_or(())
+    }
+}
+
+// The running optimizer and the generation it spawned
+#[derive(Default, Resource)]
+pub struct ParameterSearch {
+    optimizer: Option<Box<dyn ParameterOptimizer>>,
+    algorithm: TrainingAlgorithm,
+    topology: Option<Topology>,
+    weight_init: WeightInit, // Scheme of the network the search started from
+    population_size: usize,
+    spawned_generation: usize,
+}
+
+impl ParameterSearch {
+    // True if the generation that just crashed was asked from an optimizer of this
+    // algorithm, topology and population size, so its fitness can be told
+    pub fn is_running(
+        &self,
+        algorithm: TrainingAlgorithm,
+        topology: &Topology,
+        population_size: usize,
+        generation: usize,
+    ) -> bool {
+        self.optimizer.is_some()
+            && self.algorithm == algorithm
+            && self
+                .topology
+                .as_ref()
+                .is_some_and(|running| running.is_compatible(topology))
+            && self.population_size == population_size
+            && self.spawned_generation == generation
+    }
+
+    pub fn start(
+        &mut self,
+        optimizer: Box<dyn ParameterOptimizer>,
+        algorithm: TrainingAlgorithm,
+        topology: Topology,
+        weight_init: WeightInit,
+        population_size: usize,
+    ) {
+        self.optimizer = Some(optimizer);
+        self.algorithm = algorithm;
+        self.topology = Some(topology);
+        self.weight_init = weight_init;
+        self.population_size = population_size;
+    }
+
+    pub fn weight_init(&self) -> WeightInit {
+        self.weight_init
+    }
+
+    pub fn tell(&mut self, fitness: &[f32]) {
+        if let Some(optimizer) = self.optimizer.as_mut() {
+            optimizer.tell(fitness);
+        }
+    }
+
+    // Genomes for the given generation
+    pub fn ask(
+        &mut self,
+        generation: usize,
+        params: &GuiParameters,
+        rng: &mut StdRng,
+    ) -> Vec<Vec<f32>> {
+        self.spawned_generation = generation;
+        match self.optimizer.as_mut() {
+            Some(optimizer) => {
+                optimizer.configure(params);
+                optimizer.ask(rng)
+            }
+            None => Vec::new(),
+        }
+    }
+
+    pub fn status(&self) -> Option<String> {
+        self.optimizer.as_ref().map(|optimizer| optimizer.status())
+    }
+}
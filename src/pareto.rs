@@ -0,0 +1,188 @@
+// Multi-objective selection in the style of NSGA-II (Deb et al. 2002). Birds are sorted
+// into fronts of mutually non-dominated flights over survival time, pipes passed and
+// flap efficiency, and spread out within a front by their crowding distance.
+use bevy::prelude::*;
+
+use crate::selection::Individual;
+use crate::simulation::FIXED_TIMESTEP;
+
+// One bird of the first front
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParetoPoint {
+    pub fitness: f32,   // Seconds alive
+    pub score: f32,     // Pipes passed
+    pub flap_rate: f32, // Share of steps with a jump, lower is more efficient
+}
+
+impl ParetoPoint {
+    pub fn new(individual: &Individual) -> Self {
+        let flap_rate = if individual.fitness > 0.0 {
+            individual.flaps as f32 * FIXED_TIMESTEP / individual.fitness
+        } else {
+            0.0
+        };
+        Self {
+            fitness: individual.fitness,
+            score: individual.score,
+            flap_rate,
+        }
+    }
+
+    // Every objective, oriented so that higher is better
+    fn objectives(&self) -> [f32; 3] {
+        [self.fitness, self.score, -self.flap_rate]
+    }
+
+    // At least as good in every objective and better in one
+    pub fn dominates(&self, other: &ParetoPoint) -> bool {
+        let (a, b) = (self.objectives(), other.objectives());
+        a.iter().zip(&b).all(|(a, b)| a >= b) && a.iter().zip(&b).any(|(a, b)| a > b)
+    }
+}
+
+// Indices of the points in each front, the non-dominated front first
+pub fn non_dominated_sort(points: &[ParetoPoint]) -> Vec<Vec<usize>> {
+    let mut dominated_by: Vec<Vec<usize>> = vec![Vec::new(); points.len()];
+    let mut domination_count = vec![0; points.len()];
+    for (i, a) in points.iter().enumerate() {
+        for (j, b) in points.iter().enumerate().skip(i + 1) {
+            if a.dominates(b) {
+                dominated_by[i].push(j);
+                domination_count[j] += 1;
+            } else if b.dominates(a) {
+                dominated_by[j].push(i);
+                domination_count[i] += 1;
+            }
+        }
+    }
+    let mut fronts = Vec::new();
+    let mut front: Vec<usize> = (0..points.len())
+        .filter(|index| domination_count[*index] == 0)
+        .collect();
+    while !front.is_empty() {
+        let mut next = Vec::new();
+        for index in &front {
+            for dominated in &dominated_by[*index] {
+                domination_count[*dominated] -= 1;
+                if domination_count[*dominated] == 0 {
+                    next.push(*dominated);
+                }
+            }
+        }
+        fronts.push(std::mem::replace(&mut front, next));
+    }
+    fronts
+}
+
+// Crowding distance of every point of a front, in the order of front. The extremes of
+// every objective are infinitely far from the crowd.
+pub fn crowding_distance(points: &[ParetoPoint], front: &[usize]) -> Vec<f32> {
+    let mut distance = vec![0.0; front.len()];
+    for objective in 0..3 {
+        let value = |position: usize| points[front[position]].objectives()[objective];
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|a, b| value(*a).total_cmp(&value(*b)));
+        let (Some(&lowest), Some(&highest)) = (order.first(), order.last()) else {
+            continue;
+        };
+        distance[lowest] = f32::INFINITY;
+        distance[highest] = f32::INFINITY;
+        let range = value(highest) - value(lowest);
+        if range <= 0.0 {
+            continue;
+        }
+        for window in order.windows(3) {
+            distance[window[1]] += (value(window[2]) - value(window[0])) / range;
+        }
+    }
+    distance
+}
+
+// The distinct flights of the first front of the last generation bred with the Pareto
+// objective, longest first
+#[derive(Clone, Debug, Default, Resource)]
+pub struct ParetoFront(pub Vec<ParetoPoint>);
+
+impl ParetoFront {
+    // Replace the fitness of every individual with a value that orders the population
+    // like NSGA-II's crowded comparison: every front above the next, and within a front
+    // the less crowded individuals first. Remembers the first front.
+    pub fn score(&mut self, population: &mut [Individual]) {
+        let points: Vec<ParetoPoint> = population.iter().map(ParetoPoint::new).collect();
+        let fronts = non_dominated_sort(&points);
+        for (rank, front) in fronts.iter().enumerate() {
+            let distance = crowding_distance(&points, front);
+            let most_isolated = distance
+                .iter()
+                .copied()
+                .filter(|d| d.is_finite())
+                .fold(f32::EPSILON, f32::max);
+            for (index, distance) in front.iter().zip(distance) {
+                // Crowding adds less than one so it never lifts an individual past a front
+                let spread = if distance.is_finite() {
+                    0.99 * distance / most_isolated
+                } else {
+                    0.999
+                };
+                population[*index].fitness = (fronts.len() - rank) as f32 + spread;
+            }
+        }
+        self.0 = fronts
+            .first()
+            .map(|front| front.iter().map(|index| points[*index]).collect())
+            .unwrap_or_default();
+        // Many birds crash in exactly the same way, only list each flight once
+        self.0.sort_by(|a, b| {
+            b.fitness
+                .total_cmp(&a.fitness)
+                .then(b.score.total_cmp(&a.score))
+                .then(a.flap_rate.total_cmp(&b.flap_rate))
+        });
+        self.0.dedup();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(fitness: f32, score: f32, flap_rate: f32) -> ParetoPoint {
+        ParetoPoint {
+            fitness,
+            score,
+            flap_rate,
+        }
+    }
+
+    #[test]
+    fn points_are_sorted_into_fronts() {
+        let points = [
+            point(10.0, 3.0, 0.2), // Dominated by 2
+            point(5.0, 1.0, 0.1),  // Flaps least, first front
+            point(12.0, 4.0, 0.2), // First front
+            point(4.0, 0.0, 0.3),  // Dominated by everyone
+            point(10.0, 3.0, 0.2), // Same flight as 0, neither dominates the other
+        ];
+        let mut fronts = non_dominated_sort(&points);
+        for front in &mut fronts {
+            front.sort();
+        }
+        assert_eq!(fronts, vec![vec![1, 2], vec![0, 4], vec![3]]);
+    }
+
+    #[test]
+    fn extremes_of_a_front_are_least_crowded() {
+        let points = [
+            point(1.0, 0.0, 0.1),
+            point(2.0, 0.0, 0.2),
+            point(3.0, 0.0, 0.3),
+            point(9.0, 0.0, 0.9),
+        ];
+        let front = [0, 1, 2, 3];
+        assert_eq!(non_dominated_sort(&points), vec![front.to_vec()]);
+        let distance = crowding_distance(&points, &front);
+        assert!(distance[0].is_infinite() && distance[3].is_infinite());
+        // Point 2 has a wider gap around it than point 1
+        assert!(distance[2] > distance[1] && distance[1] > 0.0);
+    }
+}
@@ -0,0 +1,199 @@
+// Particle swarm optimization (Kennedy and Eberhart 1995). Every bird's genome is a
+// particle that keeps its velocity and is pulled towards its own best genome and the
+// best genome of its neighbourhood after every generation.
+use rand::prelude::*;
+
+use crate::gui::GuiParameters;
+use crate::initialization::WeightInit;
+use crate::neural_network::NeuralNetwork;
+use crate::optimizer::ParameterOptimizer;
+
+// Largest change of a single gene in one generation, so the swarm cannot explode
+const MAX_VELOCITY: f32 = 1.0;
+
+// Which particles share their best genome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SwarmTopology {
+    #[default]
+    Global, // Every particle follows the best of the swarm
+    Ring, // Every particle follows the best of itself and its two neighbours
+}
+
+impl SwarmTopology {
+    pub const ALL: [SwarmTopology; 2] = [SwarmTopology::Global, SwarmTopology::Ring];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SwarmTopology::Global => "Global Best",
+            SwarmTopology::Ring => "Ring",
+        }
+    }
+}
+
+struct Particle {
+    position: Vec<f32>,
+    velocity: Vec<f32>,
+    best_position: Vec<f32>,
+    best_fitness: f32, // Negative infinity until the particle has flown
+}
+
+pub struct ParticleSwarm {
+    topology: SwarmTopology,
+    inertia: f32,
+    cognitive: f32, // Pull towards the particle's own best
+    social: f32,    // Pull towards the neighbourhood best
+    particles: Vec<Particle>,
+    evaluated: bool, // True once the current positions have flown
+}
+
+impl ParticleSwarm {
+    // The first particle starts at start, the others at fresh networks of the same topology
+    // drawn with weight_init
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        start: &NeuralNetwork,
+        weight_init: WeightInit,
+        topology: SwarmTopology,
+        inertia: f32,
+        cognitive: f32,
+        social: f32,
+        population_size: usize,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let network_topology = start.topology();
+        let particles = (0..population_size.max(1))
+            .map(|index| {
+                let position = if index == 0 {
+                    start.to_genome()
+                } else {
+                    NeuralNetwork::from_topology(&network_topology, weight_init, rng).to_genome()
+                };
+                Particle {
+                    velocity: (0..position.len())
+                        .map(|_| rng.gen_range(-0.1..0.1))
+                        .collect(),
+                    best_position: position.clone(),
+                    position,
+                    best_fitness: f32::NEG_INFINITY,
+                }
+            })
+            .collect();
+        Self {
+            topology,
+            inertia,
+            cognitive,
+            social,
+            particles,
+            evaluated: false,
+        }
+    }
+
+    // The candidate particle with the best personal fitness
+    fn fittest(&self, candidates: impl IntoIterator<Item = usize>) -> Option<usize> {
+        candidates.into_iter().max_by(|a, b| {
+            self.particles[*a]
+                .best_fitness
+                .total_cmp(&self.particles[*b].best_fitness)
+        })
+    }
+
+    // Index of the particle whose best genome each particle follows
+    fn leaders(&self) -> Vec<usize> {
+        let count = self.particles.len();
+        match self.topology {
+            SwarmTopology::Global => vec![self.fittest(0..count).unwrap_or(0); count],
+            SwarmTopology::Ring => (0..count)
+                .map(|particle| {
+                    let neighbours = [
+                        (particle + count - 1) % count,
+                        particle,
+                        (particle + 1) % count,
+                    ];
+                    self.fittest(neighbours).unwrap_or(particle)
+                })
+                .collect(),
+        }
+    }
+
+    fn best_fitness(&self) -> f32 {
+        self.particles
+            .iter()
+            .map(|particle| particle.best_fitness)
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+}
+
+impl ParameterOptimizer for ParticleSwarm {
+    // Move every particle once its current position has flown
+    fn ask(&mut self, rng: &mut StdRng) -> Vec<Vec<f32>> {
+        if self.evaluated {
+            let leaders: Vec<Vec<f32>> = self
+                .leaders()
+                .into_iter()
+                .map(|leader| self.particles[leader].best_position.clone())
+                .collect();
+            for (particle, leader) in self.particles.iter_mut().zip(&leaders) {
+                for (((x, v), best), lead) in particle
+                    .position
+                    .iter_mut()
+                    .zip(particle.velocity.iter_mut())
+                    .zip(&particle.best_position)
+                    .zip(leader)
+                {
+                    let (r1, r2) = (rng.gen::<f32>(), rng.gen::<f32>());
+                    *v = (self.inertia * *v
+                        + self.cognitive * r1 * (best - *x)
+                        + self.social * r2 * (lead - *x))
+                        .clamp(-MAX_VELOCITY, MAX_VELOCITY);
+                    *x += *v;
+                }
+            }
+            self.evaluated = false;
+        }
+        self.particles
+            .iter()
+            .map(|particle| particle.position.clone())
+            .collect()
+    }
+
+    fn tell(&mut self, fitness: &[f32]) {
+        if fitness.len() != self.particles.len() || self.evaluated {
+            return;
+        }
+        for (particle, fitness) in self.particles.iter_mut().zip(fitness) {
+            if *fitness > particle.best_fitness {
+                particle.best_fitness = *fitness;
+                particle.best_position.clone_from(&particle.position);
+            }
+        }
+        self.evaluated = true;
+    }
+
+    fn configure(&mut self, params: &GuiParameters) {
+        self.topology = params.swarm_topology;
+        self.inertia = params.swarm_inertia;
+        self.cognitive = params.swarm_cognitive;
+        self.social = params.swarm_social;
+    }
+
+    fn status(&self) -> String {
+        let speed = self
+            .particles
+            .iter()
+            .flat_map(|particle| particle.velocity.iter())
+            .map(|v| v.abs())
+            .sum::<f32>()
+            / self
+                .particles
+                .iter()
+                .map(|particle| particle.velocity.len())
+                .sum::<usize>()
+                .max(1) as f32;
+        format!(
+            "{} | best personal fitness {:.2} | mean speed {:.4}",
+            self.topology.name(),
+            self.best_fitness(),
+            speed
+        )
+    }
+}
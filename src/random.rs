@@ -0,0 +1,40 @@
+use bevy::prelude::*;
+use rand::prelude::*;
+
+// Every stream is seeded from the run seed mixed with its own constant, so consuming
+// numbers from one stream never shifts the numbers drawn from another.
+const WEIGHT_INIT_STREAM: u64 = 0x9e37_79b9_7f4a_7c15;
+const MUTATION_STREAM: u64 = 0xbf58_476d_1ce4_e5b9;
+const COURSE_STREAM: u64 = 0x94d0_49bb_1331_11eb;
+const SELECTION_STREAM: u64 = 0xd6e8_feb8_6659_fd93;
+const CROSSOVER_STREAM: u64 = 0xa076_1d64_78bd_642f;
+const SEARCH_STREAM: u64 = 0xe703_7ed1_a0b4_28db;
+const SUPERVISED_STREAM: u64 = 0x2545_f491_4f6c_dd1d;
+
+// All randomness of a training run. The same seed reproduces the same run.
+#[derive(Clone, Debug, Resource)]
+pub struct TrainingRng {
+    pub seed: u64,
+    pub weight_init: StdRng, // Weights and biases of new networks
+    pub mutation: StdRng,    // Mutation of child networks
+    pub course: StdRng,      // Pipe gap positions
+    pub selection: StdRng,   // Choice of parents
+    pub crossover: StdRng,   // Recombination of parents
+    pub search: StdRng,      // Samples of the parameter optimizers
+    pub supervised: StdRng,  // Order of the samples during supervised training
+}
+
+impl TrainingRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            weight_init: StdRng::seed_from_u64(seed ^ WEIGHT_INIT_STREAM),
+            mutation: StdRng::seed_from_u64(seed ^ MUTATION_STREAM),
+            course: StdRng::seed_from_u64(seed ^ COURSE_STREAM),
+            selection: StdRng::seed_from_u64(seed ^ SELECTION_STREAM),
+            crossover: StdRng::seed_from_u64(seed ^ CROSSOVER_STREAM),
+            search: StdRng::seed_from_u64(seed ^ SEARCH_STREAM),
+            supervised: StdRng::seed_from_u64(seed ^ SUPERVISED_STREAM),
+        }
+    }
+}
@@ -0,0 +1,119 @@
+// Draws the simulation. These systems only read the simulation, so the headless
+// app simply leaves them out.
+use crate::components::*;
+use crate::gui::*;
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+
+#[derive(Clone, Debug, Resource)]
+pub struct SpriteAssets {
+    pub quad: Handle<Mesh>,
+    pub bird_material: Handle<ColorMaterial>,
+    pub pipe_material: Handle<ColorMaterial>,
+}
+
+pub fn setup_sprite_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(SpriteAssets {
+        quad: meshes.add(Mesh::from(shape::Quad::default())),
+        bird_material: materials.add(ColorMaterial::from(Color::RED)),
+        pipe_material: materials.add(ColorMaterial::from(Color::GREEN)),
+    });
+}
+
+fn sprite_bundle(
+    mesh: &Handle<Mesh>,
+    material: &Handle<ColorMaterial>,
+    translation: Vec3,
+    scale: Vec3,
+    visibility: Visibility,
+) -> MaterialMesh2dBundle<ColorMaterial> {
+    MaterialMesh2dBundle {
+        mesh: Mesh2dHandle(mesh.clone()),
+        transform: Transform {
+            translation,
+            scale,
+            ..Default::default()
+        },
+        material: material.clone(),
+        visibility,
+        ..default()
+    }
+}
+
+// Give newly spawned birds a sprite
+pub fn add_bird_sprites(
+    mut commands: Commands,
+    bird_query: Query<(Entity, &Bird), Added<Bird>>,
+    simulation: Res<Simulation>,
+    sprite_assets: Res<SpriteAssets>,
+    params: Res<GuiParameters>,
+) {
+    let config = &simulation.config;
+    for (entity, bird) in bird_query.iter() {
+        let visibility = if bird.index < params.number_of_visible_bird {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        commands.entity(entity).insert(sprite_bundle(
+            &sprite_assets.quad,
+            &sprite_assets.bird_material,
+            Vec3::new(config.bird_x, simulation.birds()[bird.index].y, 0.0),
+            Vec3::new(config.bird_size, config.bird_size, 0.0),
+            visibility,
+        ));
+    }
+}
+
+pub fn sync_bird_sprites(
+    mut bird_query: Query<(&Bird, &mut Transform)>,
+    simulation: Res<Simulation>,
+) {
+    for (bird, mut transform) in bird_query.iter_mut() {
+        transform.translation.y = simulation.birds()[bird.index].y;
+    }
+}
+
+// Move pipe sprites with the simulated pipes, spawning and despawning them as needed
+pub fn sync_pipe_sprites(
+    mut commands: Commands,
+    mut pipe_query: Query<(Entity, &Pipe, &mut Transform)>,
+    simulation: Res<Simulation>,
+    sprite_assets: Res<SpriteAssets>,
+    mut next_pipe_id: Local<u64>,
+) {
+    for (entity, pipe, mut transform) in pipe_query.iter_mut() {
+        match simulation.pipes().iter().find(|pair| pair.id == pipe.id) {
+            Some(pair) => transform.translation.x = pair.x,
+            None => commands.entity(entity).despawn(),
+        }
+    }
+
+    let config = &simulation.config;
+    let first_new_id = *next_pipe_id;
+    for pair in simulation
+        .pipes()
+        .iter()
+        .filter(|pair| pair.id >= first_new_id)
+    {
+        // Bottom and top half, each as tall as the screen
+        let offset = config.height / 2.0 + pair.gap / 2.0;
+        for y in [pair.gap_center - offset, pair.gap_center + offset] {
+            commands.spawn((
+                Pipe { id: pair.id },
+                sprite_bundle(
+                    &sprite_assets.quad,
+                    &sprite_assets.pipe_material,
+                    Vec3::new(pair.x, y, 0.0),
+                    Vec3::new(config.pipe_width, config.height, 0.0),
+                    Visibility::Visible,
+                ),
+            ));
+        }
+        *next_pipe_id = pair.id + 1;
+    }
+}
@@ -0,0 +1,239 @@
+// Parent selection over the evaluated population of a generation
+use std::cmp::Ordering;
+
+use rand::prelude::*;
+
+use crate::neural_network::NeuralNetwork;
+
+// A network together with the results its bird achieved
+#[derive(Clone, Debug)]
+pub struct Individual {
+    pub neural_network: NeuralNetwork,
+    pub fitness: f32,
+    pub score: f32,
+    pub flaps: u32,          // Jumps of its bird, zero if not flown this generation
+    pub behaviour: Vec<f32>, // See novelty::behaviour, empty if not flown this generation
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionStrategy {
+    Tournament { size: usize }, // Fittest of `size` individuals drawn at random
+    Roulette,                   // Chance proportional to fitness
+    Rank,                       // Chance proportional to rank, the fittest has the highest rank
+    Truncation { fraction: f32 }, // Uniformly from the fittest fraction of the population
+}
+
+impl Default for SelectionStrategy {
+    fn default() -> Self {
+        SelectionStrategy::Tournament { size: 3 }
+    }
+}
+
+impl SelectionStrategy {
+    // One of each kind with its default setting, for the GUI
+    pub const ALL: [SelectionStrategy; 4] = [
+        SelectionStrategy::Tournament { size: 3 },
+        SelectionStrategy::Roulette,
+        SelectionStrategy::Rank,
+        SelectionStrategy::Truncation { fraction: 0.25 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SelectionStrategy::Tournament { .. } => "Tournament",
+            SelectionStrategy::Roulette => "Roulette",
+            SelectionStrategy::Rank => "Rank",
+            SelectionStrategy::Truncation { .. } => "Truncation",
+        }
+    }
+
+    // True if both are the same kind of strategy, whatever their settings
+    pub fn same_kind(&self, other: &SelectionStrategy) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    // Pick `count` parents, with replacement. Returns nothing if the population is empty.
+    pub fn select<'a>(
+        &self,
+        population: &'a [Individual],
+        count: usize,
+        rng: &mut impl Rng,
+    ) -> Vec<&'a Individual> {
+        if population.is_empty() {
+            return Vec::new();
+        }
+        let n = population.len();
+        let indices: Vec<usize> = match *self {
+            SelectionStrategy::Tournament { size } => (0..count)
+                .map(|_| {
+                    (0..size.max(1))
+                        .map(|_| rng.gen_range(0..n))
+                        .max_by(|a, b| compare(&population[*a], &population[*b]))
+                        .unwrap_or(0)
+                })
+                .collect(),
+            SelectionStrategy::Roulette => {
+                let weights: Vec<f32> = population.iter().map(|ind| ind.fitness.max(0.0)).collect();
+                spin(&weights, count, rng)
+            }
+            SelectionStrategy::Rank => {
+                // Rank n for the fittest down to 1 for the least fit
+                let order = fitness_order(population);
+                let weights: Vec<f32> = (0..n).map(|position| (n - position) as f32).collect();
+                spin(&weights, count, rng)
+                    .into_iter()
+                    .map(|position| order[position])
+                    .collect()
+            }
+            SelectionStrategy::Truncation { fraction } => {
+                let order = fitness_order(population);
+                let kept = ((n as f32 * fraction).ceil() as usize).clamp(1, n);
+                (0..count).map(|_| order[rng.gen_range(0..kept)]).collect()
+            }
+        };
+        indices
+            .into_iter()
+            .map(|index| &population[index])
+            .collect()
+    }
+}
+
+// Draw `count` indices with chances proportional to the weights, uniformly if all are zero
+fn spin(weights: &[f32], count: usize, rng: &mut impl Rng) -> Vec<usize> {
+    let mut cumulative = Vec::with_capacity(weights.len());
+    let mut total = 0.0;
+    for weight in weights {
+        total += weight;
+        cumulative.push(total);
+    }
+    (0..count)
+        .map(|_| {
+            if total <= 0.0 {
+                return rng.gen_range(0..weights.len());
+            }
+            let target = rng.gen_range(0.0..total);
+            cumulative
+                .partition_point(|sum| *sum <= target)
+                .min(weights.len() - 1)
+        })
+        .collect()
+}
+
+// Order by fitness, then by score
+fn compare(a: &Individual, b: &Individual) -> Ordering {
+    a.fitness
+        .total_cmp(&b.fitness)
+        .then(a.score.total_cmp(&b.score))
+}
+
+// Indices of the population from fittest to least fit. Ties keep population order.
+fn fitness_order(population: &[Individual]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..population.len()).collect();
+    order.sort_by(|a, b| compare(&population[*b], &population[*a]));
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initialization::WeightInit;
+    use crate::neural_network::{Activation, Topology};
+
+    // Individuals that only differ in fitness, which also identifies them
+    fn population(fitness: &[f32]) -> Vec<Individual> {
+        let topology = Topology::new(&[4, 1], &[Activation::Sigmoid], &[]);
+        let neural_network = NeuralNetwork::from_topology(
+            &topology,
+            WeightInit::Xavier,
+            &mut StdRng::seed_from_u64(0),
+        );
+        fitness
+            .iter()
+            .map(|fitness| Individual {
+                neural_network: neural_network.clone(),
+                fitness: *fitness,
+                score: 0.0,
+                flaps: 0,
+                behaviour: Vec::new(),
+            })
+            .collect()
+    }
+
+    fn selected_fitness(strategy: SelectionStrategy, population: &[Individual]) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(9);
+        let parents = strategy.select(population, 2_000, &mut rng);
+        assert_eq!(parents.len(), 2_000);
+        parents.iter().map(|parent| parent.fitness).collect()
+    }
+
+    fn count(selected: &[f32], fitness: f32) -> usize {
+        selected.iter().filter(|f| **f == fitness).count()
+    }
+
+    #[test]
+    fn nothing_is_selected_from_an_empty_population() {
+        for strategy in SelectionStrategy::ALL {
+            assert!(strategy
+                .select(&[], 10, &mut StdRng::seed_from_u64(1))
+                .is_empty());
+        }
+    }
+
+    #[test]
+    fn tournament_favours_the_fittest() {
+        let population = population(&[1.0, 2.0, 3.0, 4.0, 5.0]);
+        // A tournament of one is a uniform draw
+        let uniform = selected_fitness(SelectionStrategy::Tournament { size: 1 }, &population);
+        let tournament = selected_fitness(SelectionStrategy::Tournament { size: 3 }, &population);
+        assert!(count(&tournament, 5.0) > 2 * count(&uniform, 5.0));
+        assert!(count(&tournament, 1.0) < count(&uniform, 1.0) / 2);
+        // A huge tournament almost surely contains the fittest
+        let huge = selected_fitness(SelectionStrategy::Tournament { size: 100 }, &population);
+        assert_eq!(count(&huge, 5.0), huge.len());
+    }
+
+    #[test]
+    fn roulette_never_picks_individuals_without_fitness() {
+        let population = population(&[0.0, 1.0, -2.0, 3.0, 0.0]);
+        let selected = selected_fitness(SelectionStrategy::Roulette, &population);
+        assert!(selected.iter().all(|fitness| *fitness > 0.0));
+        // Chances are proportional to fitness, 1 to 3
+        let ratio = count(&selected, 3.0) as f32 / count(&selected, 1.0) as f32;
+        assert!((2.5..3.5).contains(&ratio), "{}", ratio);
+    }
+
+    #[test]
+    fn roulette_draws_uniformly_when_nobody_has_fitness() {
+        let population = population(&[0.0, -1.0, 0.0, -3.0]);
+        let selected = selected_fitness(SelectionStrategy::Roulette, &population);
+        for fitness in [-1.0, -3.0] {
+            assert!((400..600).contains(&count(&selected, fitness)));
+        }
+    }
+
+    #[test]
+    fn rank_depends_on_order_not_on_fitness() {
+        // The fittest has rank 3 of 1 + 2 + 3, however far ahead it is
+        let population = population(&[1.0, 1000.0, 2.0]);
+        let selected = selected_fitness(SelectionStrategy::Rank, &population);
+        let share = |fitness| count(&selected, fitness) as f32 / selected.len() as f32;
+        assert!((share(1000.0) - 0.5).abs() < 0.05);
+        assert!((share(2.0) - 1.0 / 3.0).abs() < 0.05);
+        assert!((share(1.0) - 1.0 / 6.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn truncation_only_draws_from_the_kept_fraction() {
+        let population = population(&[3.0, 8.0, 1.0, 6.0, 2.0, 7.0, 4.0, 5.0]);
+        let selected = selected_fitness(
+            SelectionStrategy::Truncation { fraction: 0.25 },
+            &population,
+        );
+        assert!(selected.iter().all(|fitness| *fitness >= 7.0));
+        assert!(count(&selected, 7.0) > 0 && count(&selected, 8.0) > 0);
+        // Even a tiny fraction keeps the fittest
+        let selected =
+            selected_fitness(SelectionStrategy::Truncation { fraction: 0.0 }, &population);
+        assert!(selected.iter().all(|fitness| *fitness == 8.0));
+    }
+}
@@ -0,0 +1,423 @@
+// Bevy-independent Flappy Bird simulation. The Bevy systems only decide actions,
+// call FlappyWorld::step and draw the result, so a genome can also be evaluated
+// by driving a FlappyWorld directly.
+use rand::prelude::*;
+
+pub const WINDOW_WIDTH: f32 = 1280.0;
+pub const WINDOW_HEIGHT: f32 = 720.0;
+pub const GAP_WIDTH: f32 = 125.0;
+pub const PIPE_VELOCITY: f32 = 200.0;
+pub const PIPE_WIDTH: f32 = 50.0;
+pub const PIPE_INTERVAL: f32 = 3.0; // Seconds between two pipes
+pub const BIRD_SIZE: f32 = 15.0;
+pub const SPAWN_X_POINT: f32 = -300.0;
+pub const GRAVITY: f32 = 25.82;
+pub const JUMP_FORCE: f32 = 7.0;
+pub const FORCE_SCALING: f32 = 45.0; // Converts bird velocity to pixels per second
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0; // Seconds simulated per physics step
+pub const HEIGHT_SAMPLE_INTERVAL: f32 = 0.5; // Seconds between two entries of BirdState::heights
+pub const HEIGHT_SAMPLES: usize = 20; // Heights recorded over the first ten seconds of a flight
+
+#[derive(Clone, Debug)]
+pub struct PhysicsConfig {
+    pub timestep: f32,
+    pub gravity: f32,
+    pub jump_force: f32,
+    pub force_scaling: f32,
+    pub width: f32,
+    pub height: f32,
+    pub gap_width: f32,
+    pub pipe_velocity: f32,
+    pub pipe_width: f32,
+    pub pipe_interval: f32,
+    pub bird_size: f32,
+    pub bird_x: f32,
+}
+
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            timestep: FIXED_TIMESTEP,
+            gravity: GRAVITY,
+            jump_force: JUMP_FORCE,
+            force_scaling: FORCE_SCALING,
+            width: WINDOW_WIDTH,
+            height: WINDOW_HEIGHT,
+            gap_width: GAP_WIDTH,
+            pipe_velocity: PIPE_VELOCITY,
+            pipe_width: PIPE_WIDTH,
+            pipe_interval: PIPE_INTERVAL,
+            bird_size: BIRD_SIZE,
+            bird_x: SPAWN_X_POINT,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BirdState {
+    pub y: f32,
+    pub velocity: f32,
+    pub score: f32,   // Number of pipe pairs passed
+    pub fitness: f32, // Seconds alive
+    pub dead: bool,
+    pub next_pipe: u64, // Id of the first pipe pair this bird has not cleared yet
+    pub flaps: u32,     // Number of jumps
+    pub heights: Vec<f32>, // y every HEIGHT_SAMPLE_INTERVAL seconds, at most HEIGHT_SAMPLES
+}
+
+impl BirdState {
+    fn spawned(next_pipe: u64) -> Self {
+        Self {
+            y: 0.0,
+            velocity: 0.0,
+            score: 0.0,
+            fitness: 0.0,
+            dead: false,
+            next_pipe,
+            flaps: 0,
+            heights: Vec::new(),
+        }
+    }
+}
+
+// A top and a bottom pipe with a gap between them
+#[derive(Clone, Debug)]
+pub struct PipePair {
+    pub id: u64, // Unique for the lifetime of the world
+    pub x: f32,
+    pub gap: f32,        // Width of gap
+    pub gap_center: f32, // Vertical center of gap
+}
+
+// What a bird sees, in the order fed to the neural network
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Observation {
+    pub bird_y: f32,
+    pub velocity: f32,
+    pub horizontal_distance: f32,   // To the center of the next pipe
+    pub vertical_gap_position: f32, // Center of the gap of the next pipe
+}
+
+impl Observation {
+    pub fn as_inputs(&self) -> [f32; 4] {
+        [
+            self.bird_y,
+            self.velocity,
+            self.horizontal_distance,
+            self.vertical_gap_position,
+        ]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FlappyWorld {
+    pub config: PhysicsConfig,
+    birds: Vec<BirdState>,
+    pipes: Vec<PipePair>,
+    observations: Vec<Observation>,
+    course_rng: StdRng,
+    next_pipe_id: u64,
+    time: f32,
+    time_since_last_pipe: f32,
+}
+
+impl FlappyWorld {
+    pub fn new(config: PhysicsConfig, bird_count: usize, seed: u64) -> Self {
+        let mut world = Self {
+            config,
+            birds: vec![BirdState::spawned(0); bird_count],
+            pipes: Vec::new(),
+            observations: Vec::new(),
+            course_rng: StdRng::seed_from_u64(seed),
+            next_pipe_id: 0,
+            time: 0.0,
+            time_since_last_pipe: 0.0,
+        };
+        world.reset(seed);
+        world
+    }
+
+    // Respawn every bird and start a new course. The course only depends on the seed.
+    pub fn reset(&mut self, seed: u64) {
+        self.course_rng = StdRng::seed_from_u64(seed);
+        self.birds.fill(BirdState::spawned(self.next_pipe_id));
+        self.pipes.clear();
+        self.time = 0.0;
+        // The first pipe appears on the first step
+        self.time_since_last_pipe = self.config.pipe_interval;
+        self.update_observations();
+    }
+
+    // Change the number of birds. Takes effect at the next reset.
+    pub fn set_bird_count(&mut self, bird_count: usize) {
+        self.birds
+            .resize(bird_count, BirdState::spawned(self.next_pipe_id));
+        self.observations.resize(bird_count, Observation::default());
+    }
+
+    // Advance the world by one timestep. actions[i] is true if bird i jumps.
+    pub fn step(&mut self, actions: &[bool]) -> &[Observation] {
+        if self.is_done() {
+            return &self.observations;
+        }
+        let dt = self.config.timestep;
+        self.time += dt;
+        self.move_birds(actions, dt);
+        self.move_pipes(dt);
+        self.update_scores_and_collisions();
+        self.update_observations();
+        &self.observations
+    }
+
+    // True once every bird has crashed
+    pub fn is_done(&self) -> bool {
+        self.birds.iter().all(|bird| bird.dead)
+    }
+
+    pub fn birds(&self) -> &[BirdState] {
+        &self.birds
+    }
+
+    pub fn pipes(&self) -> &[PipePair] {
+        &self.pipes
+    }
+
+    pub fn observations(&self) -> &[Observation] {
+        &self.observations
+    }
+
+    // Seconds simulated since the last reset
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    // Score of the best bird that is still flying, 0 once all have crashed
+    pub fn best_living_score(&self) -> f32 {
+        self.birds
+            .iter()
+            .filter(|bird| !bird.dead)
+            .map(|bird| bird.score)
+            .fold(0.0, f32::max)
+    }
+
+    pub fn dead_count(&self) -> usize {
+        self.birds.iter().filter(|bird| bird.dead).count()
+    }
+
+    fn move_birds(&mut self, actions: &[bool], dt: f32) {
+        let config = &self.config;
+        let top = config.height / 2.0 - config.bird_size / 2.0;
+        let bottom = -config.height / 2.0 + config.bird_size / 2.0;
+        for (bird, jump) in self.birds.iter_mut().zip(actions) {
+            if bird.dead {
+                continue;
+            }
+            if *jump {
+                bird.velocity += config.jump_force;
+                bird.flaps += 1;
+            }
+            bird.velocity -= config.gravity * dt;
+            // Birds cannot leave the screen, they stop at the edge instead
+            if bird.y > top {
+                bird.velocity = 0.0;
+                bird.y = top - 0.1;
+            }
+            if bird.y < bottom {
+                bird.velocity = 0.0;
+                bird.y = bottom + 0.1;
+            }
+            bird.y += bird.velocity * dt * config.force_scaling;
+            bird.fitness += dt;
+            if bird.heights.len() < HEIGHT_SAMPLES
+                && bird.fitness >= bird.heights.len() as f32 * HEIGHT_SAMPLE_INTERVAL
+            {
+                bird.heights.push(bird.y);
+            }
+        }
+    }
+
+    fn move_pipes(&mut self, dt: f32) {
+        self.time_since_last_pipe += dt;
+        if self.time_since_last_pipe >= self.config.pipe_interval {
+            self.time_since_last_pipe = 0.0;
+            self.spawn_pipe();
+        }
+        for pipe in self.pipes.iter_mut() {
+            pipe.x -= self.config.pipe_velocity * dt;
+        }
+        let despawn_x = -self.config.width / 2.0 - self.config.pipe_width / 2.0;
+        self.pipes.retain(|pipe| pipe.x >= despawn_x);
+    }
+
+    fn spawn_pipe(&mut self) {
+        let half_height = self.config.height / 2.0;
+        let gap = self.config.gap_width;
+        let gap_center = self
+            .course_rng
+            .gen_range((-half_height + gap)..(half_height - gap));
+        self.pipes.push(PipePair {
+            id: self.next_pipe_id,
+            x: self.config.width / 2.0 - self.config.pipe_width,
+            gap,
+            gap_center,
+        });
+        self.next_pipe_id += 1;
+    }
+
+    fn update_scores_and_collisions(&mut self) {
+        let config = &self.config;
+        for bird in self.birds.iter_mut().filter(|bird| !bird.dead) {
+            for pipe in self.pipes.iter() {
+                // Pipes are cleared in order, so each pair counts once per bird
+                if pipe.x < config.bird_x && pipe.id >= bird.next_pipe {
+                    bird.next_pipe = pipe.id + 1;
+                    bird.score += 1.0;
+                }
+                if hits_pipe(config, bird.y, pipe) {
+                    bird.dead = true;
+                }
+            }
+        }
+    }
+
+    fn update_observations(&mut self) {
+        let config = &self.config;
+        // Before the first pipe arrives, birds see where it will appear
+        let mut horizontal_distance = config.width / 2.0 - config.pipe_width - config.bird_x;
+        let mut vertical_gap_position = 0.0;
+        if let Some(pipe) = self
+            .pipes
+            .iter()
+            .filter(|pipe| pipe.x >= config.bird_x)
+            .min_by(|a, b| a.x.total_cmp(&b.x))
+        {
+            horizontal_distance = pipe.x - config.bird_x;
+            vertical_gap_position = pipe.gap_center;
+        }
+        self.observations.clear();
+        self.observations
+            .extend(self.birds.iter().map(|bird| Observation {
+                bird_y: bird.y,
+                velocity: bird.velocity,
+                horizontal_distance,
+                vertical_gap_position,
+            }));
+    }
+}
+
+// Axis aligned overlap between the bird and the top or bottom half of a pipe
+fn hits_pipe(config: &PhysicsConfig, bird_y: f32, pipe: &PipePair) -> bool {
+    let reach_x = (config.bird_size + config.pipe_width) / 2.0;
+    if (config.bird_x - pipe.x).abs() >= reach_x {
+        return false;
+    }
+    let bird_top = bird_y + config.bird_size / 2.0;
+    let bird_bottom = bird_y - config.bird_size / 2.0;
+    bird_top > pipe.gap_center + pipe.gap / 2.0 || bird_bottom < pipe.gap_center - pipe.gap / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Flaps whenever the bird sinks below the centre of the next gap
+    fn follow_gap(observation: &Observation) -> bool {
+        observation.bird_y < observation.vertical_gap_position - 10.0 && observation.velocity < 0.0
+    }
+
+    #[test]
+    fn reset_respawns_every_bird() {
+        let mut world = FlappyWorld::new(PhysicsConfig::default(), 3, 7);
+        for _ in 0..100 {
+            world.step(&[true, false, true]);
+        }
+        assert!(world.time() > 0.0);
+        world.reset(7);
+        assert_eq!(world.time(), 0.0);
+        assert!(world.pipes().is_empty());
+        for bird in world.birds() {
+            assert_eq!(
+                (bird.y, bird.velocity, bird.score, bird.fitness),
+                (0.0, 0.0, 0.0, 0.0)
+            );
+            assert!(!bird.dead);
+        }
+    }
+
+    #[test]
+    fn step_runs_until_every_bird_has_crashed() {
+        // Birds that never jump sink to the floor, below every gap, and crash into the first pipe
+        let mut world = FlappyWorld::new(PhysicsConfig::default(), 2, 1);
+        world.step(&[false, false]);
+        assert!(!world.is_done());
+        let mut steps = 1;
+        while !world.is_done() {
+            world.step(&[false, false]);
+            steps += 1;
+            assert!(steps < 10_000, "birds never crashed");
+        }
+        assert_eq!(world.dead_count(), 2);
+        assert!(world.birds().iter().all(|bird| bird.score == 0.0));
+
+        let time = world.time();
+        world.step(&[true, true]);
+        assert_eq!(world.time(), time, "a finished world does not advance");
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_the_same_flight() {
+        let mut first = FlappyWorld::new(PhysicsConfig::default(), 2, 42);
+        let mut second = FlappyWorld::new(PhysicsConfig::default(), 2, 42);
+        for step in 0..2_000 {
+            let actions = [step % 11 == 0, step % 17 == 0];
+            first.step(&actions);
+            second.step(&actions);
+            for (a, b) in first.birds().iter().zip(second.birds()) {
+                assert_eq!(
+                    (a.y, a.velocity, a.score, a.dead),
+                    (b.y, b.velocity, b.score, b.dead)
+                );
+            }
+            assert_eq!(first.observations(), second.observations());
+        }
+
+        let gaps = |seed| {
+            let mut world = FlappyWorld::new(PhysicsConfig::default(), 1, seed);
+            world.step(&[false]);
+            world.pipes()[0].gap_center
+        };
+        assert_eq!(gaps(42), gaps(42));
+        assert_ne!(gaps(42), gaps(43));
+    }
+
+    #[test]
+    fn each_bird_scores_once_per_pipe_pair_it_clears() {
+        let config = PhysicsConfig::default();
+        let bird_x = config.bird_x;
+        let mut world = FlappyWorld::new(config, 2, 3);
+        let mut cleared = 0;
+        while world.birds()[0].score < 3.0 && !world.birds()[0].dead {
+            let before: Vec<(u64, f32)> = world.pipes().iter().map(|p| (p.id, p.x)).collect();
+            let score = world.birds()[0].score;
+            let jump = follow_gap(&world.observations()[0]);
+            world.step(&[jump, false]);
+            // A pair counts when it moves past the bird, no matter how many steps it overlaps
+            cleared += before
+                .iter()
+                .filter(|(id, x)| {
+                    *x >= bird_x
+                        && world
+                            .pipes()
+                            .iter()
+                            .any(|pipe| pipe.id == *id && pipe.x < bird_x)
+                })
+                .count();
+            assert!(world.birds()[0].score - score <= 1.0);
+            assert_eq!(world.birds()[0].score, cleared as f32);
+        }
+        assert_eq!(world.birds()[0].score, 3.0, "the gap follower crashed");
+        // The bird that never jumped crashed into the first pair and kept its own score
+        assert!(world.birds()[1].dead);
+        assert_eq!(world.birds()[1].score, 0.0);
+    }
+}
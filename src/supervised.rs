@@ -0,0 +1,260 @@
+// Supervised training of fixed-architecture networks by backpropagation, so the genetic
+// algorithm can start from a network that imitates recorded demonstrations instead of
+// from random weights.
+use rand::prelude::*;
+
+use crate::dataset::Dataset;
+use crate::neural_network::{Activation, NeuralNetwork};
+use crate::openai_es::{ADAM_BETA1, ADAM_BETA2, ADAM_EPSILON};
+
+// Keeps the logarithms of the cross-entropy finite
+const PROBABILITY_EPSILON: f32 = 1e-7;
+
+// How far the network's outputs are from the demonstrated ones
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Loss {
+    MeanSquaredError,
+    #[default]
+    BinaryCrossEntropy, // Treats every output as the probability of jumping
+}
+
+impl Loss {
+    pub const ALL: [Loss; 2] = [Loss::MeanSquaredError, Loss::BinaryCrossEntropy];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Loss::MeanSquaredError => "Mean Squared Error",
+            Loss::BinaryCrossEntropy => "Binary Cross-Entropy",
+        }
+    }
+
+    // Mean loss over the outputs of one sample
+    pub fn value(&self, output: &[f32], target: &[f32]) -> f32 {
+        let total: f32 = output
+            .iter()
+            .zip(target)
+            .map(|(y, t)| match self {
+                Loss::MeanSquaredError => (y - t) * (y - t),
+                Loss::BinaryCrossEntropy => {
+                    let y = y.clamp(PROBABILITY_EPSILON, 1.0 - PROBABILITY_EPSILON);
+                    -(t * y.ln() + (1.0 - t) * (1.0 - y).ln())
+                }
+            })
+            .sum();
+        total / output.len().max(1) as f32
+    }
+
+    // Gradient of the loss with respect to the weighted sums of the output layer, whose
+    // activation turned sums into output
+    pub fn output_delta(
+        &self,
+        activation: Activation,
+        sums: &[f32],
+        output: &[f32],
+        target: &[f32],
+    ) -> Vec<f32> {
+        let count = output.len().max(1) as f32;
+        sums.iter()
+            .zip(output)
+            .zip(target)
+            .map(|((x, y), t)| match self {
+                Loss::MeanSquaredError => 2.0 * (y - t) / count * activation.derivative(*x, *y),
+                // The sigmoid's derivative cancels out, which keeps the gradient useful
+                // when the output saturates
+                Loss::BinaryCrossEntropy if activation == Activation::Sigmoid => (y - t) / count,
+                // Outputs outside 0..1 have a clamped, flat loss and so no gradient
+                Loss::BinaryCrossEntropy => {
+                    if *y < PROBABILITY_EPSILON || *y > 1.0 - PROBABILITY_EPSILON {
+                        0.0
+                    } else {
+                        (y - t) / (y * (1.0 - y)) / count * activation.derivative(*x, *y)
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+// Settings of mini-batch gradient descent with Adam
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SupervisedTraining {
+    pub loss: Loss,
+    pub learning_rate: f32,
+    pub batch_size: usize,
+    pub epochs: usize, // Passes over the whole dataset
+}
+
+impl Default for SupervisedTraining {
+    fn default() -> Self {
+        Self {
+            loss: Loss::default(),
+            learning_rate: 0.01,
+            batch_size: 32,
+            epochs: 100,
+        }
+    }
+}
+
+impl SupervisedTraining {
+    // Train the network on the dataset, visiting the samples in a new random order every
+    // epoch. Returns the mean loss of every epoch.
+    //
+    // Observations are in pixels, far outside the range where sigmoids have a gradient, so
+    // the network learns on inputs standardized to zero mean and unit deviation. The
+    // standardization is then folded into its first layer, so it takes raw observations
+    // like every other network.
+    pub fn train(
+        &self,
+        network: &mut NeuralNetwork,
+        dataset: &Dataset,
+        rng: &mut impl Rng,
+    ) -> Vec<f32> {
+        let (mean, deviation) = input_statistics(dataset);
+        let inputs: Vec<[f32; 4]> = dataset
+            .samples
+            .iter()
+            .map(|sample| {
+                let mut inputs = sample.inputs;
+                for ((input, mean), deviation) in inputs.iter_mut().zip(&mean).zip(&deviation) {
+                    *input = (*input - mean) / deviation;
+                }
+                inputs
+            })
+            .collect();
+        let mut gradient = vec![0.0; network.genes().count()];
+        let mut order: Vec<usize> = (0..dataset.len()).collect();
+        let mut losses = Vec::with_capacity(self.epochs);
+        // Adam follows running averages of every gene's gradient and of its square, so
+        // genes whose gradient is small, such as deep in a stack of sigmoids, still move
+        let mut first_moment = vec![0.0; gradient.len()];
+        let mut second_moment = vec![0.0; gradient.len()];
+        let mut steps = 0;
+        for _ in 0..self.epochs {
+            order.shuffle(rng);
+            let mut total = 0.0;
+            for batch in order.chunks(self.batch_size.max(1)) {
+                gradient.fill(0.0);
+                for index in batch {
+                    total += network.backpropagate(
+                        &inputs[*index],
+                        &[dataset.samples[*index].target()],
+                        self.loss,
+                        &mut gradient,
+                    );
+                }
+                steps += 1;
+                let step_size = self.learning_rate * (1.0 - ADAM_BETA2.powi(steps)).sqrt()
+                    / (1.0 - ADAM_BETA1.powi(steps));
+                let count = batch.len() as f32;
+                for (((gene, g), m), v) in network
+                    .genes_mut()
+                    .zip(&gradient)
+                    .zip(&mut first_moment)
+                    .zip(&mut second_moment)
+                {
+                    let g = g / count;
+                    *m = ADAM_BETA1 * *m + (1.0 - ADAM_BETA1) * g;
+                    *v = ADAM_BETA2 * *v + (1.0 - ADAM_BETA2) * g * g;
+                    *gene -= step_size * *m / (v.sqrt() + ADAM_EPSILON);
+                }
+            }
+            losses.push(total / dataset.len().max(1) as f32);
+        }
+        network.fold_input_standardization(&mean, &deviation);
+        losses
+    }
+}
+
+// Mean and standard deviation of every input over a dataset. Inputs that never change get
+// a deviation of one, so they are only centered.
+fn input_statistics(dataset: &Dataset) -> ([f32; 4], [f32; 4]) {
+    let count = dataset.len().max(1) as f32;
+    let mut mean = [0.0; 4];
+    for sample in &dataset.samples {
+        for (mean, input) in mean.iter_mut().zip(&sample.inputs) {
+            *mean += input / count;
+        }
+    }
+    let mut deviation = [0.0f32; 4];
+    for sample in &dataset.samples {
+        for ((deviation, input), mean) in deviation.iter_mut().zip(&sample.inputs).zip(&mean) {
+            *deviation += (input - mean) * (input - mean) / count;
+        }
+    }
+    for deviation in &mut deviation {
+        *deviation = if *deviation > f32::EPSILON {
+            deviation.sqrt()
+        } else {
+            1.0
+        };
+    }
+    (mean, deviation)
+}
+
+// Share of samples on which the network makes the demonstrated decision, jumping when its
+// output is above 0.5 like the birds do. Every sample starts from a zero hidden state.
+pub fn accuracy(network: &NeuralNetwork, dataset: &Dataset) -> f32 {
+    let mut hidden_state = vec![0.0; network.hidden_state_size()];
+    let correct = dataset
+        .samples
+        .iter()
+        .filter(|sample| {
+            hidden_state.fill(0.0);
+            let output = network.forward(&sample.inputs, &mut hidden_state);
+            (output[0] > 0.5) == sample.jump
+        })
+        .count();
+    correct as f32 / dataset.len().max(1) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dataset::Demonstration;
+    use crate::initialization::WeightInit;
+    use crate::neural_network::Topology;
+    use crate::simulation::{FlappyWorld, PhysicsConfig};
+
+    // Raw observations of a bird that flaps whenever it sinks below the centre of the gap
+    fn gap_follower_dataset(steps: usize) -> Dataset {
+        let mut world = FlappyWorld::new(PhysicsConfig::default(), 1, 11);
+        let mut samples = Vec::with_capacity(steps);
+        for step in 0..steps {
+            let observation = world.observations()[0];
+            let jump = observation.bird_y < observation.vertical_gap_position - 10.0
+                && observation.velocity < 0.0;
+            samples.push(Demonstration {
+                inputs: observation.as_inputs(),
+                jump,
+            });
+            world.step(&[jump]);
+            if world.is_done() {
+                world.reset(step as u64);
+            }
+        }
+        Dataset { samples }
+    }
+
+    #[test]
+    fn training_learns_demonstrations_from_raw_observations() {
+        let dataset = gap_follower_dataset(3_000);
+        let jumps = dataset.samples.iter().filter(|sample| sample.jump).count();
+        let majority = jumps.max(dataset.len() - jumps) as f32 / dataset.len() as f32;
+
+        let mut rng = StdRng::seed_from_u64(2);
+        let topology = Topology::new(&[4, 8, 1], &[Activation::Sigmoid; 2], &[]);
+        let mut network = NeuralNetwork::from_topology(&topology, WeightInit::Xavier, &mut rng);
+        let losses = SupervisedTraining::default().train(&mut network, &dataset, &mut rng);
+
+        assert!(losses[losses.len() - 1] < 0.5 * losses[0], "{:?}", losses);
+        // Always predicting the more common decision is not enough, the network has to get
+        // at least half of the remaining samples right
+        let accuracy = accuracy(&network, &dataset);
+        assert!(
+            accuracy > 0.5 * (1.0 + majority),
+            "{} vs {}",
+            accuracy,
+            majority
+        );
+    }
+}
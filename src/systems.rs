@@ -1,342 +1,763 @@
+use crate::batch::BatchedPopulation;
 use crate::components::*;
+use crate::crossover::CrossoverOperator;
+use crate::dataset::{Dataset, Demonstration};
 use crate::gui::*;
+use crate::hall_of_fame::*;
+use crate::mutation::{mutate, MutationOperator};
+use crate::neat::*;
 use crate::network_size;
 use crate::neural_network::*;
-use crate::WINDOW_HEIGHT;
+use crate::novelty::*;
+use crate::optimizer::*;
+use crate::pareto::*;
+use crate::random::*;
+use crate::selection::*;
+use crate::simulation::*;
+use crate::supervised;
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb::collide;
-use bevy::sprite::collide_aabb::Collision;
-use bevy::sprite::MaterialMesh2dBundle;
+use rand::prelude::*;
+use std::path::Path;
 
-pub const GRAVITY: f32 = 25.82;
-pub const JUMP_FORCE: f32 = 7.0;
-
-pub fn gravity_system(mut query: Query<&mut Bird>, time: Res<Time>, params: Res<GuiParameters>) {
-    if !params.start_training {
+pub fn rebuild_population_brains(
+    bird_query: Query<&Bird>,
+    changed_query: Query<(), Changed<Bird>>,
+    mut brains: ResMut<PopulationBrains>,
+) {
+    if changed_query.is_empty() {
         return;
     }
-    for mut bird in query.iter_mut() {
-        bird.velocity -= GRAVITY * time.delta_seconds();
-    }
+    brains.networks =
+        BatchedPopulation::new(bird_query.iter().filter_map(|bird| match &bird.brain {
+            Brain::Network(neural_network) => {
+                Some((bird.index, neural_network, &bird.hidden_state[..]))
+            }
+            Brain::Neat(_) | Brain::Human => None,
+        }));
+    brains.neat = bird_query
+        .iter()
+        .filter_map(|bird| match &bird.brain {
+            Brain::Neat(genome) => Some((bird.index, genome.compile())),
+            Brain::Network(_) | Brain::Human => None,
+        })
+        .collect();
 }
 
 pub fn jump_system(
-    mut query: Query<(&mut Bird, &Environment, &Transform)>,
-    input: Res<Input<KeyCode>>,
+    mut brains: ResMut<PopulationBrains>,
+    mut bird_query: Query<&mut Bird>,
+    simulation: Res<Simulation>,
+    mut actions: ResMut<BirdActions>,
+    mut human: ResMut<HumanPlay>,
     params: Res<GuiParameters>,
+    mut hidden_states: Local<Vec<Vec<f32>>>,
 ) {
     if !params.start_training {
         return;
     }
-    for (mut bird, environment, transform) in query.iter_mut() {
-        if input.just_pressed(KeyCode::Space) {
-            bird.velocity += JUMP_FORCE;
-            println!("Jumped!");
+    let observations = simulation.observations();
+    actions.0.clear();
+    actions.0.resize(observations.len(), false);
+    hidden_states.resize(observations.len(), Vec::new());
+    let actions = &mut actions.0;
+    brains.networks.forward(
+        |index| observations[index].as_inputs(),
+        |index, output, hidden_state| {
+            actions[index] = output[0] > 0.5;
+            hidden_states[index].clear();
+            hidden_states[index].extend_from_slice(hidden_state);
+        },
+    );
+    for (index, network) in brains.neat.iter_mut() {
+        let output = network.forward(&observations[*index].as_inputs());
+        actions[*index] = output[0] > 0.5;
+    }
+    // The player's bird jumps on Space, and every step it flies is recorded with the
+    // inputs a network would have seen
+    let jump = std::mem::take(&mut human.jump_requested);
+    for bird in bird_query.iter() {
+        if matches!(bird.brain, Brain::Human) && !simulation.birds()[bird.index].dead {
+            actions[bird.index] = jump;
+            human.recording.push(Demonstration {
+                inputs: observations[bird.index].as_inputs(),
+                jump,
+            });
         }
-        let input = vec![
-            transform.translation.y,
-            bird.velocity,
-            environment.horizontal_distance,
-            environment.vertical_gap_position,
-        ];
-        let output = bird.neural_network.forward(&input);
-        if output[0] > 0.5 {
-            bird.velocity += JUMP_FORCE;
+    }
+    // Hand the new hidden states back to the birds without marking them changed, which
+    // would rebuild every brain on the next step
+    for mut bird in bird_query.iter_mut() {
+        if let Some(hidden_state) = hidden_states.get(bird.index) {
+            if !bird.hidden_state.is_empty() {
+                let bird = bird.bypass_change_detection();
+                bird.hidden_state.copy_from_slice(hidden_state);
+            }
         }
     }
 }
 
-pub fn move_bird(
-    mut query: Query<(&mut Bird, &mut Transform)>,
-    time: Res<Time>,
-    params: Res<GuiParameters>,
+pub fn step_simulation(
+    mut simulation: ResMut<Simulation>,
+    actions: Res<BirdActions>,
+    mut params: ResMut<GuiParameters>,
 ) {
     if !params.start_training {
         return;
     }
-    for (mut bird, mut transform) in query.iter_mut() {
-        if transform.translation.y > WINDOW_HEIGHT / 2.0 - BIRD_SIZE / 2.0 {
-            bird.velocity = 0.0;
-            transform.translation.y = WINDOW_HEIGHT / 2.0 - BIRD_SIZE / 2.0 - 0.1;
-        }
-        if transform.translation.y < -WINDOW_HEIGHT / 2.0 + BIRD_SIZE / 2.0 {
-            bird.velocity = 0.0;
-            transform.translation.y = -WINDOW_HEIGHT / 2.0 + BIRD_SIZE / 2.0 + 0.1;
-        }
-
-        transform.translation.y += bird.velocity * time.delta_seconds() * params.force_scaling;
+    simulation.step(&actions.0);
+    params.passed_time_since_start = simulation.time();
+    if !simulation.is_done() {
+        // Keep showing the last score once the generation has crashed
+        params.current_score = simulation.best_living_score();
     }
+    params.dead_bird_count = simulation.dead_count();
 }
 
 pub fn check_collision(
     mut commands: Commands,
-    mut bird_query: Query<(Entity, &Transform, &mut Bird)>,
-    mut pipe_query: Query<(&Transform, With<Pipe>)>,
+    bird_query: Query<(Entity, &Bird)>,
+    simulation: Res<Simulation>,
     mut params: ResMut<GuiParameters>,
-    mut best_birds: ResMut<BestBirds>,
+    mut hall_of_fame: ResMut<HallOfFame>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    mut neat: ResMut<NeatPopulation>,
 ) {
-    if !params.start_training {
+    if !params.start_training || params.generation_dead {
         return;
     }
 
-    for (ent, bird_transform, mut bird) in bird_query.iter_mut() {
-        for (pipe_transform, _pipe) in pipe_query.iter_mut() {
-            let collision = collide(
-                bird_transform.translation,
-                Vec2::new(BIRD_SIZE, BIRD_SIZE),
-                pipe_transform.translation,
-                Vec2::new(PIPE_WIDTH, WINDOW_HEIGHT),
-            );
+    // Handle crashed birds in simulation order so ties are always broken the same way
+    let mut dead_birds: Vec<(Entity, &Bird)> = bird_query
+        .iter()
+        .filter(|(_, bird)| simulation.birds()[bird.index].dead)
+        .collect();
+    dead_birds.sort_by_key(|(_, bird)| bird.index);
 
-            if let Some(collision) = collision {
-                match collision {
-                    Collision::Left => {
-                        bird.dead = true;
-                        params.dead_bird_count += 1;
-                    }
-                    Collision::Right => {
-                        // Past pipe
-                    }
-                    Collision::Top => {
-                        bird.dead = true;
-                        params.dead_bird_count += 1;
-                    }
-                    Collision::Bottom => {
-                        bird.dead = true;
-                        params.dead_bird_count += 1;
-                    }
-                    Collision::Inside => {
-                        // Inside pipe
-                    }
-                }
+    for (ent, bird) in dead_birds {
+        let state = &simulation.birds()[bird.index];
+        match &bird.brain {
+            Brain::Network(neural_network) => {
+                let individual = Individual {
+                    neural_network: neural_network.clone(),
+                    fitness: state.fitness,
+                    score: state.score,
+                    flaps: state.flaps,
+                    behaviour: behaviour(state),
+                };
+                hall_of_fame.consider(&individual, params.current_generation);
+                evaluated.0.push(individual);
             }
+            Brain::Neat(_) => neat.record(bird.index, state.fitness, state.score),
+            Brain::Human => {}
         }
-        if bird.dead {
-            if bird.fitness > best_birds.best_fitness && bird.score >= best_birds.best_score {
-                // Move current best to second best
-                best_birds.second_best_fitness = best_birds.best_fitness;
-                best_birds.second_best_neural_network = best_birds.best_neural_network.clone();
-                best_birds.second_best_score = best_birds.best_score;
+        commands.entity(ent).despawn_recursive();
+    }
+    if simulation.is_done() {
+        println!(
+            "Generation: {} Current score: {}",
+            params.current_generation, params.current_score
+        );
+        params.generation_dead = true;
+    }
+}
 
-                // Update best bird data
-                best_birds.best_fitness = bird.fitness;
-                best_birds.best_neural_network = bird.neural_network.clone();
-                best_birds.best_score = bird.score;
-            } else if bird.fitness > best_birds.second_best_fitness
-                && bird.score >= best_birds.second_best_score
-            {
-                // Update second best bird data
-                best_birds.second_best_fitness = bird.fitness;
-                best_birds.second_best_neural_network = bird.neural_network.clone();
-                best_birds.second_best_score = bird.score;
-            }
-            commands.entity(ent).despawn_recursive();
+// Brains of the first generation of a run: random networks, minimal NEAT genomes or the
+// player
+pub fn first_generation_brains(
+    params: &GuiParameters,
+    rng: &mut TrainingRng,
+    neat: &mut NeatPopulation,
+) -> Vec<Brain> {
+    match params.brain_kind {
+        BrainKind::Network => {
+            let topology = params.topology();
+            (0..params.population_size)
+                .map(|_| {
+                    Brain::Network(NeuralNetwork::from_topology(
+                        &topology,
+                        params.weight_init,
+                        &mut rng.weight_init,
+                    ))
+                })
+                .collect()
+        }
+        BrainKind::Neat => {
+            *neat = new_neat_population(params, rng);
+            neat.genomes.iter().cloned().map(Brain::Neat).collect()
         }
-        if params.dead_bird_count == params.population_size {
-            println!(
-                "Generation: {} Current score: {}",
-                params.current_generation, params.current_score
+        BrainKind::Human => vec![Brain::Human],
+    }
+}
+
+// Put a bird with each brain into a freshly reset simulation
+pub fn spawn_generation(
+    commands: &mut Commands,
+    simulation: &mut FlappyWorld,
+    rng: &mut TrainingRng,
+    brains: Vec<Brain>,
+) {
+    simulation.set_bird_count(brains.len());
+    simulation.reset(rng.course.gen());
+    for (index, brain) in brains.into_iter().enumerate() {
+        let hidden_state = match &brain {
+            Brain::Network(neural_network) => vec![0.0; neural_network.hidden_state_size()],
+            Brain::Neat(_) | Brain::Human => Vec::new(),
+        };
+        commands.spawn(Bird {
+            index,
+            brain,
+            hidden_state,
+        });
+    }
+}
+
+// The crashed generation together with the hall of fame, which may also have been
+// loaded from disk. When parents are selected by novelty or Pareto rank the hall of fame
+// is left out, as its flights are not comparable with this generation's, and the fitness
+// of each individual is replaced by the selection objective.
+fn breeding_pool(
+    evaluated: &mut EvaluatedPopulation,
+    hall_of_fame: &HallOfFame,
+    archive: &mut NoveltyArchive,
+    front: &mut ParetoFront,
+    params: &GuiParameters,
+) -> Vec<Individual> {
+    let mut pool = std::mem::take(&mut evaluated.0);
+    match params.selection_objective {
+        SelectionObjective::Fitness => {
+            pool.extend(
+                compatible_entries(hall_of_fame, params).map(|entry| Individual {
+                    neural_network: entry.neural_network.clone(),
+                    fitness: entry.fitness,
+                    score: entry.score,
+                    flaps: 0,
+                    behaviour: Vec::new(),
+                }),
             );
-            params.generation_dead = true;
+        }
+        SelectionObjective::Pareto => front.score(&mut pool),
+        SelectionObjective::Novelty | SelectionObjective::Blend => {
+            archive.score(&mut pool, params.selection_objective, params.novelty_weight);
         }
     }
+    pool
+}
+
+// Hall of fame entries that fit the current network_size and recurrent layers
+fn compatible_entries<'a>(
+    hall_of_fame: &'a HallOfFame,
+    params: &GuiParameters,
+) -> impl Iterator<Item = &'a HallOfFameEntry> {
+    let topology = params.topology();
+    hall_of_fame
+        .entries()
+        .iter()
+        .filter(move |entry| entry.neural_network.topology().is_compatible(&topology))
+}
+
+// Unchanged copies of the best genomes ever seen, so mutation can never lose them
+fn elites(hall_of_fame: &HallOfFame, params: &GuiParameters) -> Vec<NeuralNetwork> {
+    compatible_entries(hall_of_fame, params)
+        .take(params.elite_count.min(params.population_size))
+        .map(|entry| entry.neural_network.clone())
+        .collect()
+}
+
+// Child of two parents, or a copy of the first parent if their genes do not line up
+fn breed(
+    operator: &dyn CrossoverOperator,
+    parent1: &NeuralNetwork,
+    parent2: &NeuralNetwork,
+    rng: &mut StdRng,
+) -> NeuralNetwork {
+    operator
+        .crossover(parent1, parent2, rng)
+        .unwrap_or_else(|err| {
+            println!("Could not cross over parents: {}", err);
+            parent1.clone()
+        })
+}
+
+fn mutate_child(
+    child: &mut NeuralNetwork,
+    params: &GuiParameters,
+    operator: &dyn MutationOperator,
+    rng: &mut StdRng,
+) {
+    if let Err(err) = mutate(child, params.mutation_probability, operator, rng) {
+        println!("Could not mutate child: {}", err);
+    }
 }
 
 // spawn new gen if generation is dead
+#[allow(dead_code)] // Alternative to generate_next_generation_thirds, see main
 pub fn generate_next_generation(
     mut commands: Commands,
     mut params: ResMut<GuiParameters>,
-    mut best_birds: ResMut<BestBirds>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut pipe_query: Query<(Entity, &Pipe)>,
+    hall_of_fame: Res<HallOfFame>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    (mut archive, mut front): (ResMut<NoveltyArchive>, ResMut<ParetoFront>),
 ) {
-    if !params.generation_dead || !params.start_training {
+    if !params.generation_dead
+        || !params.start_training
+        || params.brain_kind != BrainKind::Network
+        || params.training_algorithm != TrainingAlgorithm::Genetic
+    {
         return;
     }
-    for i in 0..params.population_size {
-        if best_birds.best_score <= 0.1 {
-            best_birds.best_neural_network = NeuralNetwork::new(&network_size);
-        }
-        if best_birds.second_best_score <= 0.1 {
-            best_birds.second_best_neural_network = NeuralNetwork::new(&network_size);
-        }
-        let mut child_neural_network = crossover_average(
-            best_birds.best_neural_network.clone(),
-            best_birds.second_best_neural_network.clone(),
+    let operator = params.mutation_operator();
+    let crossover_operator = params.crossover_operator();
+    let mut children = elites(&hall_of_fame, &params);
+    let pool = breeding_pool(
+        &mut evaluated,
+        &hall_of_fame,
+        &mut archive,
+        &mut front,
+        &params,
+    );
+    let parents = params.selection_strategy.select(
+        &pool,
+        (params.population_size - children.len()) * 2,
+        &mut rng.selection,
+    );
+    for pair in parents.chunks_exact(2) {
+        let mut child_neural_network = breed(
+            crossover_operator.as_ref(),
+            &pair[0].neural_network,
+            &pair[1].neural_network,
+            &mut rng.crossover,
         );
-        mutate(
+        mutate_child(
             &mut child_neural_network,
-            params.mutation_probability,
-            params.mutation_rate,
+            &params,
+            operator.as_ref(),
+            &mut rng.mutation,
         );
-
-        let visible: Visibility = if i < params.number_of_visible_bird {
-            Visibility::Visible
-        } else {
-            Visibility::Hidden
-        };
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    translation: Vec3::new(SPAWN_X_POINT, 0.0, 0.0),
-                    scale: Vec3::new(BIRD_SIZE, BIRD_SIZE, 0.0),
-                    ..Default::default()
-                },
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                visibility: visible,
-                ..default()
-            },
-            Bird {
-                velocity: 0.0,
-                score: 0.0,
-                fitness: 0.0,
-                dead: false,
-                neural_network: child_neural_network.clone(),
-            },
-            Environment::default(),
-        ));
+        children.push(child_neural_network);
     }
-    if params.current_generation % 10 == 0 {
+    let brains = children.into_iter().map(Brain::Network).collect();
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    if params.current_generation.is_multiple_of(10) {
         params.mutation_probability *= 0.9;
     }
     params.generation_dead = false;
     params.dead_bird_count = 0;
     params.current_score = 0.0;
     params.current_generation += 1;
-    params.passed_time_since_last_pipe = 0.0;
-    // Despawn all pipes
-    for (ent, _pipe) in pipe_query.iter_mut() {
-        commands.entity(ent).despawn_recursive();
-    }
 }
 
 pub fn generate_next_generation_thirds(
     mut commands: Commands,
     mut params: ResMut<GuiParameters>,
-    best_birds: ResMut<BestBirds>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
-    mut pipe_query: Query<(Entity, &Pipe)>,
+    hall_of_fame: Res<HallOfFame>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    (mut archive, mut front): (ResMut<NoveltyArchive>, ResMut<ParetoFront>),
 ) {
-    if !params.generation_dead || !params.start_training {
+    if !params.generation_dead
+        || !params.start_training
+        || params.brain_kind != BrainKind::Network
+        || params.training_algorithm != TrainingAlgorithm::Genetic
+    {
         return;
     }
-    let mut added_birds_counter: usize = 0;
-    for i in 0..params.population_size / 2 {
-        let mut child_neural_network = best_birds.best_neural_network.clone();
-        mutate(
+    // After the elites, a half are mutated copies of selected parents, a quarter are
+    // mutated children of two selected parents and the rest are new networks
+    let operator = params.mutation_operator();
+    let crossover_operator = params.crossover_operator();
+    let mut children = elites(&hall_of_fame, &params);
+    let pool = breeding_pool(
+        &mut evaluated,
+        &hall_of_fame,
+        &mut archive,
+        &mut front,
+        &params,
+    );
+    let bred = params.population_size - children.len();
+    let parents =
+        params
+            .selection_strategy
+            .select(&pool, bred / 2 + bred / 4 * 2, &mut rng.selection);
+    let (clones, pairs) = parents.split_at(parents.len().min(bred / 2));
+    let offspring = clones
+        .iter()
+        .map(|parent| parent.neural_network.clone())
+        .chain(pairs.chunks_exact(2).map(|pair| {
+            breed(
+                crossover_operator.as_ref(),
+                &pair[0].neural_network,
+                &pair[1].neural_network,
+                &mut rng.crossover,
+            )
+        }))
+        .collect::<Vec<_>>();
+    for mut child_neural_network in offspring {
+        mutate_child(
             &mut child_neural_network,
-            params.mutation_probability,
-            params.mutation_rate,
+            &params,
+            operator.as_ref(),
+            &mut rng.mutation,
         );
-
-        let visible: Visibility = if i < params.number_of_visible_bird {
-            Visibility::Visible
-        } else {
-            Visibility::Hidden
-        };
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    translation: Vec3::new(SPAWN_X_POINT, 0.0, 0.0),
-                    scale: Vec3::new(BIRD_SIZE, BIRD_SIZE, 0.0),
-                    ..Default::default()
-                },
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                visibility: visible,
-                ..default()
-            },
-            Bird {
-                velocity: 0.0,
-                score: 0.0,
-                fitness: 0.0,
-                dead: false,
-                neural_network: child_neural_network.clone(),
-            },
-            Environment::default(),
+        children.push(child_neural_network);
+    }
+    let topology = params.topology();
+    while children.len() < params.population_size {
+        children.push(NeuralNetwork::from_topology(
+            &topology,
+            params.weight_init,
+            &mut rng.weight_init,
         ));
-        added_birds_counter += 1;
     }
-    for i in 0..params.population_size / 4 {
-        let mut child_neural_network = best_birds.second_best_neural_network.clone();
-        mutate(
-            &mut child_neural_network,
-            params.mutation_probability,
-            params.mutation_rate,
-        );
+    let brains = children.into_iter().map(Brain::Network).collect();
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    params.generation_dead = false;
+    params.dead_bird_count = 0;
+    params.current_score = 0.0;
+    params.current_generation += 1;
+    if params.current_generation.is_multiple_of(10) {
+        params.mutation_probability *= 0.9;
+    }
+}
 
-        let visible: Visibility = if i < params.number_of_visible_bird {
-            Visibility::Visible
-        } else {
-            Visibility::Hidden
-        };
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    translation: Vec3::new(SPAWN_X_POINT, 0.0, 0.0),
-                    scale: Vec3::new(BIRD_SIZE, BIRD_SIZE, 0.0),
-                    ..Default::default()
-                },
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                visibility: visible,
-                ..default()
-            },
-            Bird {
-                velocity: 0.0,
-                score: 0.0,
-                fitness: 0.0,
-                dead: false,
-                neural_network: child_neural_network.clone(),
-            },
-            Environment::default(),
-        ));
-        added_birds_counter += 1;
+// Replace the current generation with a network trained on the recorded demonstrations
+// and mutated copies of it, so training starts from imitation instead of random weights.
+// Runs after check_collision and before the other generation systems, which leave the
+// new generation alone.
+pub fn pretrain_population(
+    mut commands: Commands,
+    bird_query: Query<(Entity, &Bird)>,
+    mut params: ResMut<GuiParameters>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    mut search: ResMut<ParameterSearch>,
+) {
+    if !params.pretrain_requested {
+        return;
+    }
+    params.pretrain_requested = false;
+    if params.brain_kind != BrainKind::Network {
+        println!("Only fixed-architecture networks can be pre-trained");
+        return;
     }
-    for i in 0..params.population_size - added_birds_counter {
-        let child_neural_network = NeuralNetwork::new(&[4, 3, 1]);
+    let dataset = match Dataset::load(Path::new(&params.dataset_path)) {
+        Ok(dataset) => dataset,
+        Err(err) => {
+            println!("Could not load dataset: {}", err);
+            return;
+        }
+    };
+    let mut network =
+        NeuralNetwork::from_topology(&params.topology(), params.weight_init, &mut rng.weight_init);
+    let losses = params
+        .supervised
+        .train(&mut network, &dataset, &mut rng.supervised);
+    println!(
+        "Pre-trained on {} samples from {} for {} epochs, loss {:.4}, accuracy {:.1}%",
+        dataset.len(),
+        params.dataset_path,
+        losses.len(),
+        losses.last().copied().unwrap_or(f32::NAN),
+        100.0 * supervised::accuracy(&network, &dataset)
+    );
 
-        let visible: Visibility = if i < params.number_of_visible_bird {
-            Visibility::Visible
-        } else {
-            Visibility::Hidden
+    let operator = params.mutation_operator();
+    let brains = (0..params.population_size)
+        .map(|index| {
+            let mut child = network.clone();
+            if index > 0 {
+                mutate_child(&mut child, &params, operator.as_ref(), &mut rng.mutation);
+            }
+            Brain::Network(child)
+        })
+        .collect();
+    // Crashed birds were already despawned by check_collision
+    for (entity, bird) in bird_query.iter() {
+        if simulation
+            .birds()
+            .get(bird.index)
+            .is_some_and(|state| !state.dead)
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    // Nothing of the replaced generation is bred from or told to an optimizer
+    evaluated.0.clear();
+    *search = ParameterSearch::default();
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    params.generation_dead = false;
+    params.dead_bird_count = 0;
+    params.current_score = 0.0;
+}
+
+// Start the run over from generation 0 with a new seed, as if the app had been launched
+// with it. Everything learned so far is dropped, only the settings are kept.
+pub fn restart_training(
+    mut commands: Commands,
+    bird_query: Query<(Entity, &Bird)>,
+    mut params: ResMut<GuiParameters>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+    (mut hall_of_fame, mut evaluated, mut search, mut neat): (
+        ResMut<HallOfFame>,
+        ResMut<EvaluatedPopulation>,
+        ResMut<ParameterSearch>,
+        ResMut<NeatPopulation>,
+    ),
+    (mut archive, mut front, mut human): (
+        ResMut<NoveltyArchive>,
+        ResMut<ParetoFront>,
+        ResMut<HumanPlay>,
+    ),
+) {
+    let Some(seed) = params.restart_seed.take() else {
+        return;
+    };
+    println!("Restarting with seed {}", seed);
+    *rng = TrainingRng::new(seed);
+    *hall_of_fame = HallOfFame::new(hall_of_fame.capacity);
+    evaluated.0.clear();
+    *search = ParameterSearch::default();
+    *neat = NeatPopulation::default();
+    *archive = NoveltyArchive::default();
+    *front = ParetoFront::default();
+    *human = HumanPlay::default();
+    // Crashed birds were already despawned by check_collision
+    for (entity, bird) in bird_query.iter() {
+        if simulation
+            .birds()
+            .get(bird.index)
+            .is_some_and(|state| !state.dead)
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let brains = first_generation_brains(&params, &mut rng, &mut neat);
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    params.generation_dead = false;
+    params.dead_bird_count = 0;
+    params.current_score = 0.0;
+    params.current_generation = 0;
+    params.passed_time_since_start = 0.0;
+}
+
+// Next generation asked from the selected parameter optimizer, which is first told the
+// fitness of the generation it asked for before
+pub fn generate_next_generation_search(
+    mut commands: Commands,
+    mut params: ResMut<GuiParameters>,
+    hall_of_fame: Res<HallOfFame>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    mut search: ResMut<ParameterSearch>,
+) {
+    if !params.generation_dead
+        || !params.start_training
+        || params.brain_kind != BrainKind::Network
+        || params.training_algorithm == TrainingAlgorithm::Genetic
+    {
+        return;
+    }
+    // The crashed birds already went through the hall of fame, nothing breeds from them here
+    evaluated.0.clear();
+    let topology = params.topology();
+    let algorithm = params.training_algorithm;
+    if search.is_running(
+        algorithm,
+        &topology,
+        params.population_size,
+        params.current_generation,
+    ) {
+        let fitness: Vec<f32> = simulation.birds().iter().map(|bird| bird.fitness).collect();
+        search.tell(&fitness);
+    } else {
+        // Start from the best network so far
+        let start = match compatible_entries(&hall_of_fame, &params).next() {
+            Some(entry) => entry.neural_network.clone(),
+            None => {
+                NeuralNetwork::from_topology(&topology, params.weight_init, &mut rng.weight_init)
+            }
         };
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: meshes.add(Mesh::from(shape::Quad::default())).into(),
-                transform: Transform {
-                    translation: Vec3::new(SPAWN_X_POINT, 0.0, 0.0),
-                    scale: Vec3::new(BIRD_SIZE, BIRD_SIZE, 0.0),
-                    ..Default::default()
-                },
-                material: materials.add(ColorMaterial::from(Color::RED)),
-                visibility: visible,
-                ..default()
-            },
-            Bird {
-                velocity: 0.0,
-                score: 0.0,
-                fitness: 0.0,
-                dead: false,
-                neural_network: child_neural_network.clone(),
+        let Some(optimizer) = params.parameter_optimizer(&start, &mut rng.search) else {
+            return;
+        };
+        println!(
+            "Starting {} with {} parameters",
+            algorithm.name(),
+            topology.parameter_count()
+        );
+        search.start(
+            optimizer,
+            algorithm,
+            topology.clone(),
+            start.weight_init(),
+            params.population_size,
+        );
+    }
+    let generation = params.current_generation + 1;
+    let weight_init = search.weight_init();
+    let brains = search
+        .ask(generation, &params, &mut rng.search)
+        .iter()
+        .map(
+            |genome| match NeuralNetwork::from_genome(&topology, genome) {
+                Ok(network) => network.with_weight_init(weight_init),
+                Err(err) => {
+                    println!("Could not build network from genome: {}", err);
+                    NeuralNetwork::from_topology(
+                        &topology,
+                        params.weight_init,
+                        &mut rng.weight_init,
+                    )
+                }
             },
-            Environment::default(),
-        ));
-        added_birds_counter += 1;
+        )
+        .map(Brain::Network)
+        .collect();
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
+    params.generation_dead = false;
+    params.dead_bird_count = 0;
+    params.current_score = 0.0;
+    params.current_generation = generation;
+}
+
+pub fn generate_next_generation_neat(
+    mut commands: Commands,
+    mut params: ResMut<GuiParameters>,
+    mut neat: ResMut<NeatPopulation>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+) {
+    if !params.generation_dead || !params.start_training || params.brain_kind != BrainKind::Neat {
+        return;
+    }
+    if neat.genomes.is_empty() {
+        // Switched over from fixed networks
+        *neat = new_neat_population(&params, &mut rng);
+    } else {
+        let operator = params.mutation_operator();
+        let settings = NeatSettings {
+            population_size: params.population_size,
+            compatibility_threshold: params.neat_compatibility_threshold,
+            add_node_probability: params.neat_add_node_probability,
+            add_connection_probability: params.neat_add_connection_probability,
+            mutation_probability: params.mutation_probability,
+            mutation_operator: operator.as_ref(),
+        };
+        neat.next_generation(&settings, &mut rng);
     }
+    let brains = neat.genomes.iter().cloned().map(Brain::Neat).collect();
+    spawn_generation(&mut commands, &mut simulation, &mut rng, brains);
     params.generation_dead = false;
     params.dead_bird_count = 0;
     params.current_score = 0.0;
     params.current_generation += 1;
-    params.passed_time_since_last_pipe = 0.0;
-    if params.current_generation % 10 == 0 {
+    if params.current_generation.is_multiple_of(10) {
         params.mutation_probability *= 0.9;
     }
-    // Despawn all pipes
-    for (ent, _pipe) in pipe_query.iter_mut() {
-        commands.entity(ent).despawn_recursive();
+}
+
+// Remember a key press until the next fixed step, which may run several frames later
+pub fn read_human_input(input: Res<Input<KeyCode>>, mut human: ResMut<HumanPlay>) {
+    if input.just_pressed(KeyCode::Space) {
+        human.jump_requested = true;
+    }
+}
+
+// Append the player's crashed flight to the dataset and start a new one. Runs before the
+// other generation systems, so a flight is saved even after switching to another brain.
+pub fn generate_next_generation_human(
+    mut commands: Commands,
+    mut params: ResMut<GuiParameters>,
+    mut human: ResMut<HumanPlay>,
+    mut simulation: ResMut<Simulation>,
+    mut rng: ResMut<TrainingRng>,
+) {
+    if !params.generation_dead || !params.start_training {
+        return;
+    }
+    if !human.recording.is_empty() {
+        match Dataset::append(Path::new(&params.dataset_path), &human.recording) {
+            Ok(()) => println!(
+                "Recorded {} steps to {}",
+                human.recording.len(),
+                params.dataset_path
+            ),
+            Err(err) => println!("Could not record demonstration: {}", err),
+        }
+        human.recording.clear();
+    }
+    if params.brain_kind != BrainKind::Human {
+        return;
+    }
+    human.jump_requested = false;
+    spawn_generation(&mut commands, &mut simulation, &mut rng, vec![Brain::Human]);
+    params.generation_dead = false;
+    params.dead_bird_count = 0;
+    params.current_score = 0.0;
+    params.current_generation += 1;
+}
+
+// Minimal genomes wired like network_size without its hidden layers
+pub fn new_neat_population(params: &GuiParameters, rng: &mut TrainingRng) -> NeatPopulation {
+    NeatPopulation::new(
+        params.population_size,
+        network_size[0],
+        network_size[network_size.len() - 1],
+        &mut rng.weight_init,
+    )
+}
+
+// Switch every living bird over when the activations are changed in the GUI. The hall of
+// fame keeps the activations its results were achieved with.
+pub fn apply_activation_changes(
+    mut bird_query: Query<&mut Bird>,
+    params: Res<GuiParameters>,
+    mut applied: Local<Vec<Activation>>,
+) {
+    if !params.is_changed() || *applied == params.layer_activations {
+        return;
+    }
+    if !applied.is_empty() {
+        for mut bird in bird_query.iter_mut() {
+            if let Brain::Network(neural_network) = &mut bird.brain {
+                neural_network.set_activations(&params.layer_activations);
+            }
+        }
+    }
+    applied.clone_from(&params.layer_activations);
+}
+
+// Same for the recurrent layers. Hidden states are resized and start again from zero. Hall
+// of fame networks keep their recurrent weights and are left out of breeding until the
+// layers match again.
+pub fn apply_recurrence_changes(
+    mut bird_query: Query<&mut Bird>,
+    params: Res<GuiParameters>,
+    mut evaluated: ResMut<EvaluatedPopulation>,
+    mut applied: Local<Vec<bool>>,
+) {
+    if !params.is_changed() || *applied == params.recurrent_layers {
+        return;
+    }
+    if !applied.is_empty() {
+        for mut bird in bird_query.iter_mut() {
+            if let Brain::Network(neural_network) = &mut bird.brain {
+                neural_network.set_recurrent_layers(&params.recurrent_layers);
+                let size = neural_network.hidden_state_size();
+                bird.hidden_state = vec![0.0; size];
+            }
+        }
+        // Crashed birds of this generation still breed the next one
+        for individual in evaluated.0.iter_mut() {
+            individual
+                .neural_network
+                .set_recurrent_layers(&params.recurrent_layers);
+        }
     }
+    applied.clone_from(&params.recurrent_layers);
 }